@@ -4,6 +4,8 @@ const LIGHT_THEME_TEXT = 'LIGHT';
 // Theme toggle functionality
 const themeToggle = document.querySelector('.theme-toggle');
 const prefersDarkScheme = window.matchMedia('(prefers-color-scheme: dark)');
+// Set when the page has a default mode, instead of following the system preference
+const defaultTheme = document.documentElement.dataset.defaultTheme;
 
 // Function to set the theme
 const setTheme = (theme) => {
@@ -19,6 +21,8 @@ const setTheme = (theme) => {
 const savedTheme = localStorage.getItem('theme');
 if (savedTheme) {
   setTheme(savedTheme);
+} else if (defaultTheme) {
+  setTheme(defaultTheme);
 } else {
   setTheme(prefersDarkScheme.matches ? 'dark' : 'light');
 }
@@ -31,7 +35,7 @@ themeToggle.addEventListener('click', () => {
 
 // Listen for system theme changes
 prefersDarkScheme.addEventListener('change', (e) => {
-  if (!localStorage.getItem('theme')) {
+  if (!localStorage.getItem('theme') && !defaultTheme) {
     setTheme(e.matches ? 'dark' : 'light');
   }
 });
@@ -1,47 +1,268 @@
-#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_errors_doc, clippy::option_if_let_else)]
 use chrono::{NaiveDate, NaiveDateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use std::collections::BTreeMap;
+
 pub mod utils;
 
 pub const ASSETS_PATH: &str = "assets";
+// The sources of the built in templates, read from disk by `serve` when they are there
+pub const TEMPLATES_PATH: &str = "src/templates";
+// Kept apart from `ASSETS_PATH`, so generated files never mix with the sources
+pub const OUTPUT_PATH: &str = "public";
 pub const CONFIG_PATH: &str = "config/config.json";
+// The config file can be in any of these formats, tried in this order
+pub const CONFIG_EXTENSIONS: &[&str] = &["json", "toml", "yaml", "yml"];
 pub const DATE_FORMAT: &str = "%Y-%m-%d";
 pub const TIME_FORMAT: &str = "%H:%M:%S";
 pub const LONG_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+// RFC 3339, all times are in UTC
+pub const FEED_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
 pub const HISTORY_PATH: &str = "config";
+// Relative to the directory of the config file
+pub const INCIDENTS_DIR: &str = "incidents";
+pub const CHECKS_DIR: &str = "checks.d";
+// Environment variables that override the paths above, the command line flags win over them
+pub const CONFIG_ENV: &str = "NANOWATCHRS_CONFIG";
+pub const HISTORY_DIR_ENV: &str = "NANOWATCHRS_HISTORY_DIR";
+pub const OUTPUT_DIR_ENV: &str = "NANOWATCHRS_OUTPUT_DIR";
 pub const HISTORY_LENGTH: usize = 30;
+// Used for checks that don't set a timeout, and have no default for it
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+// Incidents that ended longer ago than this are only shown in the archive
+pub const RECENT_INCIDENT_DAYS: i64 = 14;
+pub const INCIDENTS_PER_PAGE: usize = 20;
 
 pub type Error = Box<dyn std::error::Error>;
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct StatusPageContext {
     pub settings: SiteSettings,
+    #[serde(default)]
+    pub defaults: CheckDefaults,
     pub checks: Vec<Check>,
+    #[serde(default)]
+    pub groups: Vec<Group>,
     pub incidents: Vec<Incident>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct SiteSettings {
+    #[serde(default)]
     pub site: SiteParams,
+    #[serde(default)]
     pub page: PageParams,
+    #[serde(default)]
+    pub theme: ThemeParams,
+    #[serde(default)]
+    pub build: BuildParams,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl SiteSettings {
+    // Fills in the page settings that every template uses from the ones that are set
+    pub fn apply_fallbacks(&mut self) {
+        if self.page.title.is_none() {
+            self.page.title = Some(
+                self.site
+                    .name
+                    .as_ref()
+                    .map_or_else(|| String::from("Status"), |name| format!("{name} Status")),
+            );
+        }
+        if self.page.header.is_none() {
+            self.page.header.clone_from(&self.page.title);
+        }
+    }
+}
+
+// Colours and fonts that replace the ones in the bundled stylesheet, anything left out
+// keeps its bundled value
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeParams {
+    #[serde(default)]
+    pub default_mode: ThemeMode,
+    // Any CSS `font-family`, e.g. "Inter, sans-serif"
+    pub font_family: Option<String>,
+    // Any CSS length, e.g. "18px"
+    pub font_size: Option<String>,
+    // Used in both modes, unless the palette of the mode sets the same colour
+    #[serde(default)]
+    pub colors: ThemePalette,
+    #[serde(default)]
+    pub light: ThemePalette,
+    #[serde(default)]
+    pub dark: ThemePalette,
+}
+
+impl ThemeParams {
+    // Every value that ends up in the page's CSS, with the path of its setting
+    #[must_use]
+    pub fn values(&self) -> Vec<(Vec<&'static str>, &str)> {
+        let mut values = vec![];
+        values.extend(
+            self.font_family
+                .as_deref()
+                .map(|value| (vec!["font_family"], value)),
+        );
+        values.extend(
+            self.font_size
+                .as_deref()
+                .map(|value| (vec!["font_size"], value)),
+        );
+
+        for (palette_name, palette) in [
+            ("colors", &self.colors),
+            ("light", &self.light),
+            ("dark", &self.dark),
+        ] {
+            for (name, _, value) in palette.colors() {
+                values.extend(value.map(|value| (vec![palette_name, name], value)));
+            }
+        }
+
+        values
+    }
+}
+
+// The mode used until a visitor picks one with the toggle
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    // Follows the visitor's system preference
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+// Any CSS colour, e.g. "#0d47a1" or "rgb(13 71 161)"
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ThemePalette {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub text_alt: Option<String>,
+    pub link: Option<String>,
+    pub link_hover: Option<String>,
+    pub success: Option<String>,
+    pub warning: Option<String>,
+    pub danger: Option<String>,
+    pub failure: Option<String>,
+    pub disabled: Option<String>,
+}
+
+impl ThemePalette {
+    // Each colour's setting name, and the CSS custom property from `style.css` it replaces
+    fn colors(&self) -> [(&'static str, &'static str, Option<&str>); 10] {
+        [
+            (
+                "background",
+                "--background-color",
+                self.background.as_deref(),
+            ),
+            ("text", "--text-color", self.text.as_deref()),
+            ("text_alt", "--text-color-alt", self.text_alt.as_deref()),
+            ("link", "--link", self.link.as_deref()),
+            ("link_hover", "--link-hover", self.link_hover.as_deref()),
+            ("success", "--success", self.success.as_deref()),
+            ("warning", "--warning", self.warning.as_deref()),
+            ("danger", "--danger", self.danger.as_deref()),
+            ("failure", "--failure", self.failure.as_deref()),
+            ("disabled", "--disabled", self.disabled.as_deref()),
+        ]
+    }
+
+    #[must_use]
+    pub fn variables(&self) -> Vec<(&'static str, &str)> {
+        self.colors()
+            .into_iter()
+            .filter_map(|(_, variable, value)| Some((variable, value?)))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+pub struct BuildParams {
+    // Files in "<theme_dir>/templates" and "<theme_dir>/assets" replace the built in ones
+    pub theme_dir: Option<String>,
+    // Where the generated site is written, defaults to `OUTPUT_PATH`
+    pub output_dir: Option<String>,
+    // Where the history of each check is kept, defaults to `HISTORY_PATH`
+    pub history_dir: Option<String>,
+    // Also write gzip and brotli compressed copies of the text outputs, defaults to true
+    pub precompress: Option<bool>,
+    // Minify the generated HTML and the stylesheets
+    #[serde(default)]
+    pub minify: bool,
+    // Read the built in templates and assets from `TEMPLATES_PATH` and `ASSETS_PATH`
+    // when they exist, instead of the copies in the binary. Only set by `serve`
+    #[serde(skip)]
+    pub from_source: bool,
+}
+
+impl BuildParams {
+    #[must_use]
+    pub fn output_dir(&self) -> &str {
+        self.output_dir.as_deref().unwrap_or(OUTPUT_PATH)
+    }
+
+    #[must_use]
+    pub fn precompress(&self) -> bool {
+        self.precompress.unwrap_or(true)
+    }
+
+    #[must_use]
+    pub fn history_dir(&self) -> &str {
+        self.history_dir.as_deref().unwrap_or(HISTORY_PATH)
+    }
+}
+
+// Everything is optional, the meta tags for missing values are left out
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
 pub struct SiteParams {
-    pub name: String,
-    pub description: String,
-    pub url: String,
-    pub logo: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub logo: Option<String>,
+    // Where the status page itself is published, used for the canonical link of each page
+    pub canonical_url: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl SiteParams {
+    // The base of the absolute links in the feed and status.json, without a trailing slash.
+    // Links are relative to the root of the domain when no URL is known
+    #[must_use]
+    pub fn public_url(&self) -> &str {
+        self.canonical_url
+            .as_deref()
+            .or(self.url.as_deref())
+            .unwrap_or_default()
+            .trim_end_matches('/')
+    }
+
+    #[must_use]
+    pub fn canonical_link(&self, path: &str) -> Option<String> {
+        let canonical_url = self.canonical_url.as_deref()?.trim_end_matches('/');
+        Some(format!("{canonical_url}/{path}"))
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
 pub struct PageParams {
-    pub title: String,
-    pub header: String,
-    pub header_link: String,
-    pub subheader: String,
+    // Defaults to "<site name> Status"
+    pub title: Option<String>,
+    // Defaults to the title
+    pub header: Option<String>,
+    pub header_link: Option<String>,
+    pub subheader: Option<String>,
+    // Markdown displayed at the bottom of every page
+    pub footer: Option<String>,
+    // Added to the end of the head of every page as is, e.g. for analytics
+    pub head_html: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -56,7 +277,7 @@ pub struct StatusBlock {
     pub uptime: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum State {
     #[serde(rename = "unknown")]
@@ -89,11 +310,36 @@ impl State {
             Self::Disabled => "Unknown",
         }
     }
+
+    // Used to order states from least to most severe, disabled is lowest
+    // because it means there is no information to go off of
+    #[must_use]
+    pub const fn severity(&self) -> u8 {
+        match self {
+            Self::Disabled => 0,
+            Self::Success => 1,
+            Self::Warning => 2,
+            Self::Danger => 3,
+            Self::Failure => 4,
+        }
+    }
+
+    #[must_use]
+    pub const fn to_overall_status(&self) -> &str {
+        match self {
+            Self::Success => "All Systems Operational",
+            Self::Warning => "Degraded Performance",
+            Self::Danger => "Partial Outage",
+            Self::Failure => "Major Outage",
+            Self::Disabled => "Status Unknown",
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct HistoryEntry {
     #[serde(with = "date_format")]
+    #[schemars(with = "String")]
     pub date: NaiveDate,
     pub state: State,
     pub notes: String,
@@ -129,26 +375,128 @@ impl Default for HistoryEntry {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub struct Check {
     pub name: String,
     pub description: Option<String>,
     pub target: String,
     pub page_link: Option<String>,
-    pub expected_status: Option<u16>,
-    pub timeout_ms: u64,
-    #[serde(rename = "type")]
+    pub timeout_ms: Option<u64>,
+    // How many more times a failing check is run before the failure is recorded
+    pub retries: Option<u32>,
+    #[serde(default)]
+    pub impact: Impact,
+    pub group: Option<String>,
+    // The `type` field picks the variant, and the rest of the fields are its settings
+    #[serde(flatten)]
     pub check_type: CheckType,
-    pub port: Option<u16>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl Check {
+    #[must_use]
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)
+    }
+
+    #[must_use]
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or_default()
+    }
+
+    // Settings already on the check are kept, so the most specific defaults go first
+    pub fn apply_defaults(&mut self, defaults: &CheckDefaults) {
+        self.timeout_ms = self.timeout_ms.or(defaults.timeout_ms);
+        self.retries = self.retries.or(defaults.retries);
+
+        if let CheckType::Http(http) = &mut self.check_type {
+            http.expected_status = http.expected_status.or(defaults.expected_status);
+            for (name, value) in &defaults.headers {
+                http.headers
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+// Settings for every check that doesn't set them itself, globally or for a group. The
+// defaults of a group take priority over the global ones
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CheckDefaults {
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    // Only used by http checks
+    pub expected_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+// How much a check contributes to the overall status of the page
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+pub enum Impact {
+    // Any state is reflected as-is in the overall status
+    #[default]
+    Critical,
+    // Can at most degrade the overall status, never cause an outage
+    Informational,
+    // Not counted towards the overall status at all
+    None,
+}
+
+// Checks reference a group by its name, groups without checks are not displayed
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub order: i32,
+    #[serde(default)]
+    pub collapsed: bool,
+    #[serde(default)]
+    pub defaults: CheckDefaults,
+}
+
+// Each type of check has its own settings, so a setting on the wrong type of check,
+// or a required one that is missing, fails when the config is loaded
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum CheckType {
-    Http,
-    Ping,
-    Port,
+    Http(HttpCheck),
+    Ping(PingCheck),
+    Port(PortCheck),
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HttpCheck {
+    // Defaults to 200
+    pub expected_status: Option<u16>,
+    // Sent with every request, e.g. an API key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<BasicAuth>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PingCheck {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PortCheck {
+    pub port: u16,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -159,17 +507,201 @@ pub enum CheckResult {
     Unknown,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub struct Incident {
+    // Incidents loaded from files use the file name (without `.md`) as their id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub title: String,
-    pub description: String,
-    pub status: String,
-    pub display_date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<IncidentStatus>,
+    #[serde(default)]
+    pub severity: IncidentSeverity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_date: Option<String>,
     #[serde(with = "long_date_format")]
+    #[schemars(with = "String")]
     pub started_at: NaiveDateTime,
+    #[serde(
+        default,
+        with = "optional_long_date_format",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub resolved_at: Option<NaiveDateTime>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<IncidentUpdate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub affected_checks: Vec<String>,
+}
+
+impl Incident {
+    // The most recent update decides the status of the incident, falling back
+    // to the status set on the incident itself
+    #[must_use]
+    pub fn current_status(&self) -> Option<IncidentStatus> {
+        self.updates
+            .iter()
+            .max_by_key(|update| update.posted_at)
+            .map(|update| update.status)
+            .or(self.status)
+    }
+
+    // Used as the name of the incident's own page
+    #[must_use]
+    pub fn slug(&self) -> String {
+        match &self.id {
+            Some(id) => utils::config::slugify(id),
+            None => format!(
+                "{}-{}",
+                self.started_at.format(DATE_FORMAT),
+                utils::config::slugify(&self.title)
+            ),
+        }
+    }
+
+    // Active, upcoming, and recently ended incidents are shown on the main page
+    #[must_use]
+    pub fn is_recent(&self, now: NaiveDateTime) -> bool {
+        let cutoff = now - chrono::Duration::days(RECENT_INCIDENT_DAYS);
+
+        self.started_at > now || self.ended_at().is_none_or(|ended_at| ended_at > cutoff)
+    }
+
+    #[must_use]
+    pub fn display_date(&self) -> String {
+        match &self.display_date {
+            Some(display_date) => display_date.clone(),
+            None => self.started_at.format(DATE_FORMAT).to_string(),
+        }
+    }
+
+    // Resolved incidents without a resolution time end at their last update, scheduled
+    // incidents end at their planned resolution time, anything else is still ongoing
+    #[must_use]
+    pub fn ended_at(&self) -> Option<NaiveDateTime> {
+        match self.current_status() {
+            Some(IncidentStatus::Resolved) => self.resolved_at.or_else(|| {
+                self.updates
+                    .iter()
+                    .map(|update| update.posted_at)
+                    .max()
+                    .or(Some(self.started_at))
+            }),
+            Some(IncidentStatus::Scheduled) => self.resolved_at,
+            _ => None,
+        }
+    }
+
+    // How long the incident lasted, or has lasted so far if it is still ongoing
+    #[must_use]
+    pub fn duration(&self, now: NaiveDateTime) -> chrono::Duration {
+        let ended_at = self.ended_at().map_or(now, |ended_at| ended_at.min(now));
+
+        (ended_at - self.started_at).max(chrono::Duration::zero())
+    }
+
+    #[must_use]
+    pub fn affects(&self, check_name: &str) -> bool {
+        self.affected_checks.iter().any(|name| name == check_name)
+    }
+
+    #[must_use]
+    pub fn covers_date(&self, date: NaiveDate) -> bool {
+        let end_date = self
+            .ended_at()
+            .map_or_else(|| Utc::now().naive_utc().date(), |ended_at| ended_at.date());
+
+        self.started_at.date() <= date && date <= end_date
+    }
+
+    #[must_use]
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.started_at <= now && self.ended_at().is_none_or(|ended_at| now < ended_at)
+    }
+
+    // Unresolved incidents are coloured by how severe they are
+    #[must_use]
+    pub const fn state_for(&self, status: IncidentStatus) -> State {
+        match status {
+            IncidentStatus::Resolved => State::Success,
+            IncidentStatus::Scheduled => State::Disabled,
+            _ => self.severity.to_state(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct IncidentUpdate {
     #[serde(with = "long_date_format")]
-    pub resolved_at: NaiveDateTime,
+    #[schemars(with = "String")]
+    pub posted_at: NaiveDateTime,
+    pub status: IncidentStatus,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentStatus {
+    // Older configs only had `ongoing` for an unresolved incident
+    #[serde(alias = "Investigating", alias = "ongoing", alias = "Ongoing")]
+    Investigating,
+    #[serde(alias = "Identified")]
+    Identified,
+    #[serde(alias = "Monitoring")]
+    Monitoring,
+    #[serde(alias = "Resolved")]
+    Resolved,
+    #[serde(alias = "Scheduled")]
+    Scheduled,
+}
+
+impl IncidentStatus {
+    #[must_use]
+    pub const fn to_status(&self) -> &'static str {
+        match self {
+            Self::Investigating => "Investigating",
+            Self::Identified => "Identified",
+            Self::Monitoring => "Monitoring",
+            Self::Resolved => "Resolved",
+            Self::Scheduled => "Scheduled",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentSeverity {
+    #[default]
+    #[serde(alias = "Minor")]
+    Minor,
+    #[serde(alias = "Major")]
+    Major,
+    #[serde(alias = "Critical")]
+    Critical,
+}
+
+impl IncidentSeverity {
+    #[must_use]
+    pub const fn to_state(&self) -> State {
+        match self {
+            Self::Minor => State::Warning,
+            Self::Major => State::Danger,
+            Self::Critical => State::Failure,
+        }
+    }
+
+    #[must_use]
+    pub const fn to_severity(&self) -> &'static str {
+        match self {
+            Self::Minor => "Minor",
+            Self::Major => "Major",
+            Self::Critical => "Critical",
+        }
+    }
 }
 
 pub mod date_format {
@@ -199,6 +731,38 @@ pub mod date_format {
     }
 }
 
+pub mod optional_long_date_format {
+    // This is for optional dates in the format of "2021-01-01 12:00:00"
+    use chrono::NaiveDateTime;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    use crate::LONG_DATE_FORMAT;
+
+    pub fn serialize<S>(date: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => serializer.serialize_some(&date.format(LONG_DATE_FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Some(s) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+
+        let dt = NaiveDateTime::parse_from_str(&s, LONG_DATE_FORMAT)
+            .map_err(serde::de::Error::custom)?;
+
+        Ok(Some(dt))
+    }
+}
+
 pub mod long_date_format {
     // This is for dates in the format of "2021-01-01 12:00:00"
     use chrono::NaiveDateTime;
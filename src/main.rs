@@ -1,22 +1,129 @@
-use minijinja::context;
-use nanowatchrs::utils::checks::run_check;
+use chrono::NaiveDateTime;
+use minijinja::{context, Environment, Value};
+use nanowatchrs::utils::checks::{run_check, timed_check};
+use nanowatchrs::utils::embedded::write_static_assets;
+use nanowatchrs::utils::output::write_output_file;
+use nanowatchrs::utils::schema::{json_schema, SchemaKind};
+use nanowatchrs::utils::serve::{serve_directory, watch_stamp};
+use nanowatchrs::utils::validate::{validate_config, Severity};
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
 
 use nanowatchrs::utils::config::{
-    create_history_file, does_history_file_exist, read_config_file, read_history_file,
+    create_history_file, create_incident_file, does_history_file_exist, find_config_file,
+    incidents_path, read_config_file, read_history_file, read_incident_file, write_incident_file,
+    HistorySection, LoadedConfig,
 };
 use nanowatchrs::utils::templates::{
-    create_env, render_incident, render_status_block, write_string_to_asset_folder,
+    create_env, incident_permalinks, render_group, render_incident, render_incident_archive,
+    render_incident_feed, render_overall_status, render_status_block, render_status_json,
+    render_theme_css,
+};
+use nanowatchrs::{
+    Check, CheckResult, Group, Incident, IncidentSeverity, IncidentStatus, IncidentUpdate, Result,
+    StatusPageContext,
+};
+use nanowatchrs::{
+    ASSETS_PATH, CONFIG_ENV, HISTORY_DIR_ENV, LONG_DATE_FORMAT, OUTPUT_DIR_ENV, TEMPLATES_PATH,
 };
-use nanowatchrs::CONFIG_PATH;
-use nanowatchrs::{Check, Result, StatusPageContext};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config = read_config_file(CONFIG_PATH)
-        .unwrap_or_else(|_| panic!("Failed to read config file at '{CONFIG_PATH}'"));
+    let args = parse_args();
+
+    // Validation reports problems itself, so it runs before the config is loaded
+    if matches!(args.command, Command::Validate) {
+        validate(&args.options.config_path);
+    }
+
+    // Printing a schema doesn't need a config at all
+    if let Command::Schema(kind) = args.command {
+        println!("{}", json_schema(kind)?);
+        return Ok(());
+    }
+
+    let config = load_config(&args.options).unwrap_or_else(|e| {
+        fatal(
+            format!(
+                "Failed to read config file at '{}': {e}\nRun `nanowatchrs validate` to see every problem",
+                args.options.config_path
+            )
+            .as_str(),
+        )
+    });
+
+    // Checks run with the placeholders resolved, everything else uses the config as written
+    match args.command {
+        Command::Run {
+            mode,
+            dry_run: false,
+        } => {
+            run_checks(&config.resolved, mode).await?;
+            run_template_rendering(&config.written)?;
+        }
+        Command::Run {
+            mode,
+            dry_run: true,
+        } => dry_run_checks(&config.resolved, mode).await?,
+        Command::Render => run_template_rendering(&config.written)?,
+        Command::Serve { port } => serve(config.written, &args.options, port).await?,
+        Command::Validate => unreachable!("validate exits before the config is loaded"),
+        Command::Schema(_) => unreachable!("schema returns before the config is loaded"),
+        Command::Incident(command) => {
+            run_incident_command(&config.written, &args.options, command)?;
+        }
+    }
 
-    let filtered_checks: Vec<Check> = match parse_args() {
-        RunMode::All => config.checks.iter().cloned().collect(),
+    Ok(())
+}
+
+fn validate(config_path: &str) -> ! {
+    let diagnostics = validate_config(config_path);
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    if errors > 0 {
+        eprintln!("Found {errors} error(s) and {warnings} warning(s)");
+        std::process::exit(1);
+    }
+
+    println!("No errors found, {warnings} warning(s)");
+    std::process::exit(0);
+}
+
+// Options given on the command line take priority over the config file
+fn load_config(options: &Options) -> Result<LoadedConfig> {
+    let mut config = read_config_file(&options.config_path)?;
+
+    for build in [
+        &mut config.written.settings.build,
+        &mut config.resolved.settings.build,
+    ] {
+        if let Some(output_dir) = &options.output_dir {
+            build.output_dir = Some(output_dir.clone());
+        }
+        if let Some(history_dir) = &options.history_dir {
+            build.history_dir = Some(history_dir.clone());
+        }
+    }
+
+    Ok(config)
+}
+
+fn filter_checks(config: &StatusPageContext, mode: RunMode) -> Vec<Check> {
+    match mode {
+        RunMode::All => config.checks.clone(),
         RunMode::Some(checks) => {
             // Only run the specified checks
             config
@@ -26,11 +133,14 @@ async fn main() -> Result<()> {
                 .cloned()
                 .collect()
         }
-    };
+    }
+}
 
-    for check in &filtered_checks {
+async fn run_checks(config: &StatusPageContext, mode: RunMode) -> Result<()> {
+    let history_dir = config.settings.build.history_dir();
+    for check in &filter_checks(config, mode) {
         println!("Running check '{}'", check.name);
-        match does_history_file_exist(&check.name) {
+        match does_history_file_exist(history_dir, &check.name) {
             // Match on file does not exist
             Err(e) => {
                 println!(
@@ -40,7 +150,7 @@ async fn main() -> Result<()> {
             }
             Ok(false) => {
                 println!("No history file found for '{}', creating one", check.name);
-                match create_history_file(&check.name) {
+                match create_history_file(history_dir, &check.name) {
                     Err(e) => {
                         println!(
                             "Error encountered creating history file for '{}': '{:#?}'",
@@ -53,75 +163,548 @@ async fn main() -> Result<()> {
                 }
             }
             Ok(true) => (),
+        }
+        let _ = run_check(history_dir, check).await?;
+    }
+
+    Ok(())
+}
+
+// Runs the checks and prints the results, without touching the history or the output
+async fn dry_run_checks(config: &StatusPageContext, mode: RunMode) -> Result<()> {
+    for check in &filter_checks(config, mode) {
+        println!("Running check '{}'", check.name);
+        let result = match timed_check(check).await {
+            Ok(CheckResult::Success) => String::from("Success"),
+            Ok(CheckResult::Failure(state)) => format!("Failure ({state:?})"),
+            Ok(CheckResult::Unknown) => String::from("Unknown"),
+            Err(e) => format!("Error ({e})"),
         };
-        let _ = run_check(check).await?;
+        println!("\t[DRY RUN]: {:?} => {result}", check.name);
+    }
+
+    Ok(())
+}
+
+// Serves the output directory and renders the site again whenever the config, an
+// incident, the theme, or the built in templates and assets in the source tree
+// change. Errors are printed and the last good render is kept
+async fn serve(mut config: StatusPageContext, options: &Options, port: u16) -> Result<()> {
+    let output_dir = PathBuf::from(config.settings.build.output_dir());
+    config.settings.build.from_source = true;
+    let version = Arc::new(AtomicUsize::new(0));
+
+    if let Err(e) = run_template_rendering(&config) {
+        eprintln!("Error rendering the site: '{e:#?}'");
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    println!(
+        "Serving '{}' at http://127.0.0.1:{port}, press Ctrl+C to stop",
+        output_dir.display()
+    );
+    tokio::spawn(serve_directory(
+        listener,
+        output_dir.clone(),
+        version.clone(),
+    ));
+
+    let watched_paths = |config: &StatusPageContext| {
+        let config_dir = Path::new(&options.config_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."));
+        let mut paths = vec![
+            config_dir.to_path_buf(),
+            PathBuf::from(config.settings.build.history_dir()),
+            PathBuf::from(TEMPLATES_PATH),
+            PathBuf::from(ASSETS_PATH),
+        ];
+        paths.extend(config.settings.build.theme_dir.as_ref().map(PathBuf::from));
+        paths
+    };
+    let mut last_stamp = watch_stamp(&watched_paths(&config), &output_dir);
+
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        let stamp = watch_stamp(&watched_paths(&config), &output_dir);
+        if stamp == last_stamp {
+            continue;
+        }
+        last_stamp = stamp;
+
+        println!("Change detected, rendering the site");
+        let rendered = load_config(options).and_then(|new_config| {
+            config = new_config.written;
+            // The server keeps serving the directory it started with
+            config.settings.build.output_dir = Some(output_dir.display().to_string());
+            config.settings.build.from_source = true;
+            run_template_rendering(&config)
+        });
+
+        match rendered {
+            Err(e) => eprintln!("Error rendering the site: '{e:#?}'"),
+            Ok(()) => {
+                version.fetch_add(1, Ordering::SeqCst);
+            }
+        }
     }
+}
+
+fn run_incident_command(
+    config: &StatusPageContext,
+    options: &Options,
+    command: IncidentCommand,
+) -> Result<()> {
+    let incidents_dir = incidents_path(&options.config_path);
+    let now = chrono::Utc::now().naive_utc();
+
+    let render = match command {
+        IncidentCommand::List => {
+            list_incidents(config);
+            false
+        }
+        IncidentCommand::New {
+            title,
+            status,
+            severity,
+            message,
+            checks,
+            render,
+        } => {
+            let incident = new_incident(config, title, status, severity, message, checks, now)?;
+            let incident_path = create_incident_file(&incidents_dir, &incident)?;
+            println!("Created incident '{}'", incident_path.display());
+            render
+        }
+        IncidentCommand::Update {
+            id,
+            status,
+            message,
+            render,
+        } => {
+            let (incident_path, mut incident) = find_incident_file(&incidents_dir, &id)?;
+            update_incident(&mut incident, &id, status, message, now)?;
+            write_incident_file(&incident_path, &incident)?;
+            println!("Updated incident '{}'", incident_path.display());
+            render
+        }
+        IncidentCommand::Resolve {
+            id,
+            message,
+            render,
+        } => {
+            let (incident_path, mut incident) = find_incident_file(&incidents_dir, &id)?;
+            resolve_incident(&mut incident, &id, message, now)?;
+            write_incident_file(&incident_path, &incident)?;
+            println!("Resolved incident '{}'", incident_path.display());
+            render
+        }
+    };
 
-    run_template_rendering(&config)?;
+    if render {
+        // Read the config again so the changed incident is included
+        let config = load_config(options)?;
+        run_template_rendering(&config.written)?;
+    }
 
     Ok(())
 }
 
+fn new_incident(
+    config: &StatusPageContext,
+    title: String,
+    status: Option<IncidentStatus>,
+    severity: Option<IncidentSeverity>,
+    message: Option<String>,
+    checks: Vec<String>,
+    now: NaiveDateTime,
+) -> Result<Incident> {
+    if title.trim().is_empty() {
+        return Err("incident new: the title can not be empty".into());
+    }
+    if let Some(name) = checks
+        .iter()
+        .find(|name| !config.checks.iter().any(|check| &check.name == *name))
+    {
+        return Err(format!("incident new: there is no check named '{name}'").into());
+    }
+
+    let status = status.unwrap_or(IncidentStatus::Investigating);
+    Ok(Incident {
+        id: None,
+        title,
+        description: None,
+        status: Some(status),
+        severity: severity.unwrap_or_default(),
+        display_date: None,
+        started_at: now,
+        resolved_at: (status == IncidentStatus::Resolved).then_some(now),
+        updates: message
+            .map(|message| IncidentUpdate {
+                posted_at: now,
+                status,
+                message,
+            })
+            .into_iter()
+            .collect(),
+        affected_checks: checks,
+    })
+}
+
+fn update_incident(
+    incident: &mut Incident,
+    id: &str,
+    status: Option<IncidentStatus>,
+    message: String,
+    now: NaiveDateTime,
+) -> Result<()> {
+    let Some(status) = status.or_else(|| incident.current_status()) else {
+        return Err(format!("incident update: '{id}' has no status, set one with --status").into());
+    };
+
+    incident.status = Some(status);
+    incident.updates.push(IncidentUpdate {
+        posted_at: now,
+        status,
+        message,
+    });
+    if status == IncidentStatus::Resolved && incident.resolved_at.is_none() {
+        incident.resolved_at = Some(now);
+    }
+
+    Ok(())
+}
+
+fn resolve_incident(
+    incident: &mut Incident,
+    id: &str,
+    message: Option<String>,
+    now: NaiveDateTime,
+) -> Result<()> {
+    if incident.current_status() == Some(IncidentStatus::Resolved) {
+        return Err(format!("incident resolve: '{id}' is already resolved").into());
+    }
+
+    incident.status = Some(IncidentStatus::Resolved);
+    incident.resolved_at = Some(now);
+    incident.updates.push(IncidentUpdate {
+        posted_at: now,
+        status: IncidentStatus::Resolved,
+        message: message.unwrap_or_else(|| String::from("This incident has been resolved.")),
+    });
+
+    Ok(())
+}
+
+// Only incidents stored as files can be edited, inline incidents are left to config.json
+fn find_incident_file(incidents_dir: &Path, id: &str) -> Result<(PathBuf, Incident)> {
+    let incident_path = incidents_dir.join(format!("{id}.md"));
+
+    if !incident_path.exists() {
+        return Err(format!(
+            "No incident file found for '{id}' in '{}', run `incident list` to see the ids",
+            incidents_dir.display()
+        )
+        .into());
+    }
+
+    let incident = read_incident_file(&incident_path)?;
+    Ok((incident_path, incident))
+}
+
+fn list_incidents(config: &StatusPageContext) {
+    println!("{:<40} {:<14} {:<20} TITLE", "ID", "STATUS", "STARTED");
+    for incident in &config.incidents {
+        println!(
+            "{:<40} {:<14} {:<20} {}",
+            incident.id.as_deref().unwrap_or("(config.json)"),
+            incident
+                .current_status()
+                .map_or("Unknown", |status| status.to_status()),
+            incident.started_at.format(LONG_DATE_FORMAT).to_string(),
+            incident.title
+        );
+    }
+}
+
 fn run_template_rendering(config: &StatusPageContext) -> Result<()> {
-    let env = create_env();
+    let build = &config.settings.build;
+
+    let assets = write_static_assets(build)?;
+    let mut env = create_env(build, assets);
+    // The theme is the same on every page
+    let theme = &config.settings.theme;
+    env.add_global(
+        "theme_css",
+        Value::from_safe_string(render_theme_css(theme)),
+    );
+    env.add_global("theme_mode", Value::from_serialize(theme.default_mode));
 
     let template = env.get_template("index.html.jinja")?;
 
-    let status_blocks: Option<String> = config
+    let histories: Vec<(&Check, HistorySection)> = config
         .checks
         .iter()
-        .filter_map(|check| match read_history_file(&check.name) {
-            Err(e) => {
-                println!(
-                    "Error encountered reading history entry for '{}': '{:#?}'",
-                    check.name, e
-                );
-                None
-            }
-            Ok(history) => render_status_block(&env, check, &history).ok(),
-        })
-        .reduce(|a, b| format!("{a}\n{b}"));
+        .filter_map(
+            |check| match read_history_file(build.history_dir(), &check.name) {
+                Err(e) => {
+                    println!(
+                        "Error encountered reading history entry for '{}': '{:#?}'",
+                        check.name, e
+                    );
+                    None
+                }
+                Ok(history) => Some((check, history)),
+            },
+        )
+        .collect();
 
-    if status_blocks.is_none() {
-        return Err("Error rendering status blocks".into());
-    }
+    let overall_status = render_overall_status(&env, &histories)?;
+
+    let status_blocks = render_status_blocks(&env, config, &histories)?;
 
-    let incident_rendering = config
-        .incidents
+    let permalinks = incident_permalinks(&config.incidents);
+    let now = chrono::Utc::now().naive_utc();
+
+    let incident_rendering = permalinks
         .iter()
-        .filter_map(|incident| match render_incident(&env, incident) {
-            Err(e) => {
-                println!("Error rendering incident '{}': '{:#?}'", incident.title, e);
-                None
+        .filter(|(_, incident)| incident.is_recent(now))
+        .filter_map(|(slug, incident)| {
+            match render_incident(&env, incident, &format!("incidents/{slug}.html")) {
+                Err(e) => {
+                    println!("Error rendering incident '{}': '{:#?}'", incident.title, e);
+                    None
+                }
+                Ok(template) => Some(template),
             }
-            Ok(template) => Some(template),
         })
         .reduce(|a, b| format!("{a}\n{b}"));
 
     let context = context! {
         site => config.settings.site,
         page => config.settings.page,
-        incidents => format!("{:#?}", config.incidents),
-        rendered_blocks => status_blocks.unwrap(),
-        incidents => incident_rendering.unwrap(),
+        root => "",
+        canonical => config.settings.site.canonical_link(""),
+        overall_status => overall_status,
+        rendered_blocks => status_blocks,
+        incidents => incident_rendering,
+        has_archive => !permalinks.is_empty(),
     };
 
-    let _ = write_string_to_asset_folder("index.html", &template.render(context)?);
+    write_output_file(build, "index.html", template.render(context)?.as_bytes())?;
+
+    for (file_name, content) in render_incident_archive(&env, config, &permalinks)? {
+        write_output_file(build, &file_name, content.as_bytes())?;
+    }
+
+    write_output_file(
+        build,
+        "feed.xml",
+        render_incident_feed(&env, config, &permalinks)?.as_bytes(),
+    )?;
+    write_output_file(
+        build,
+        "status.json",
+        render_status_json(config, &histories, &permalinks)?.as_bytes(),
+    )?;
 
     Ok(())
 }
 
+// Checks without a group are displayed first, then each group in order
+fn render_status_blocks(
+    env: &Environment<'_>,
+    config: &StatusPageContext,
+    histories: &[(&Check, HistorySection)],
+) -> Result<String> {
+    let is_grouped = |check: &Check| {
+        check.group.as_ref().is_some_and(|group| {
+            let exists = config.groups.iter().any(|g| &g.name == group);
+            if !exists {
+                println!(
+                    "Group '{}' for check '{}' is not defined, displaying it ungrouped",
+                    group, check.name
+                );
+            }
+            exists
+        })
+    };
+
+    let ungrouped_blocks = histories
+        .iter()
+        .filter(|(check, _)| !is_grouped(check))
+        .filter_map(|(check, history)| {
+            render_status_block(env, check, history, &config.incidents).ok()
+        });
+
+    let mut groups = config.groups.iter().collect::<Vec<&Group>>();
+    groups.sort_by_key(|group| group.order);
+
+    let group_blocks = groups.into_iter().filter_map(|group| {
+        let checks = histories
+            .iter()
+            .filter(|(check, _)| check.group.as_ref() == Some(&group.name))
+            .cloned()
+            .collect::<Vec<(&Check, HistorySection)>>();
+
+        if checks.is_empty() {
+            return None;
+        }
+
+        match render_group(env, group, &checks, &config.incidents) {
+            Err(e) => {
+                println!("Error rendering group '{}': '{:#?}'", group.name, e);
+                None
+            }
+            Ok(template) => Some(template),
+        }
+    });
+
+    ungrouped_blocks
+        .chain(group_blocks)
+        .reduce(|a, b| format!("{a}\n{b}"))
+        .ok_or_else(|| "Error rendering status blocks".into())
+}
+
+struct Args {
+    options: Options,
+    command: Command,
+}
+
+// Options that apply to every command, the flags take priority over the environment
+struct Options {
+    config_path: String,
+    output_dir: Option<String>,
+    history_dir: Option<String>,
+}
+
+enum Command {
+    Run { mode: RunMode, dry_run: bool },
+    Render,
+    Serve { port: u16 },
+    Validate,
+    Schema(SchemaKind),
+    Incident(IncidentCommand),
+}
+
 enum RunMode {
     Some(Vec<String>),
     All,
 }
 
-fn parse_args() -> RunMode {
+enum IncidentCommand {
+    New {
+        title: String,
+        status: Option<IncidentStatus>,
+        severity: Option<IncidentSeverity>,
+        message: Option<String>,
+        checks: Vec<String>,
+        render: bool,
+    },
+    Update {
+        id: String,
+        status: Option<IncidentStatus>,
+        message: String,
+        render: bool,
+    },
+    Resolve {
+        id: String,
+        message: Option<String>,
+        render: bool,
+    },
+    List,
+}
+
+fn parse_args() -> Args {
+    let mut options = Options {
+        config_path: std::env::var(CONFIG_ENV).unwrap_or_else(|_| find_config_file()),
+        output_dir: std::env::var(OUTPUT_DIR_ENV).ok(),
+        history_dir: std::env::var(HISTORY_DIR_ENV).ok(),
+    };
+    let mut remaining = vec![];
+
+    // Options can be given anywhere, everything else is left for the command
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let Some(value) = args.next() else {
+                    fatal("--output: a directory is required");
+                };
+                options.output_dir = Some(value);
+            }
+            "--config" => {
+                let Some(value) = args.next() else {
+                    fatal("--config: a config file path is required");
+                };
+                options.config_path = value;
+            }
+            "--history" => {
+                let Some(value) = args.next() else {
+                    fatal("--history: a directory is required");
+                };
+                options.history_dir = Some(value);
+            }
+            _ => remaining.push(arg),
+        }
+    }
+
+    let mut args = remaining.into_iter().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some("incident") => {
+            args.next();
+            Command::Incident(parse_incident_args(args))
+        }
+        Some("render") => {
+            args.next();
+            if let Some(arg) = args.next() {
+                fatal(format!("render: unknown argument '{arg}'").as_str());
+            }
+            Command::Render
+        }
+        Some("validate") => {
+            args.next();
+            if let Some(arg) = args.next() {
+                fatal(format!("validate: unknown argument '{arg}'").as_str());
+            }
+            Command::Validate
+        }
+        Some("serve") => {
+            args.next();
+            parse_serve_args(args)
+        }
+        Some("schema") => {
+            args.next();
+            parse_schema_args(args)
+        }
+        _ => parse_run_args(args),
+    };
+
+    Args { options, command }
+}
+
+fn parse_schema_args(mut args: impl Iterator<Item = String>) -> Command {
+    let name = args.next().unwrap_or_else(|| String::from("config"));
+    if let Some(arg) = args.next() {
+        fatal(format!("schema: unknown argument '{arg}'").as_str());
+    }
+
+    let Some(kind) = SchemaKind::from_name(&name) else {
+        fatal(
+            format!("schema: unknown file '{name}', expected 'config', 'checks' or 'history'")
+                .as_str(),
+        );
+    };
+    Command::Schema(kind)
+}
+
+fn parse_run_args(mut args: impl Iterator<Item = String>) -> Command {
     let mut checks = vec![];
     let mut run_all = false;
+    let mut dry_run = false;
 
-    let mut args = std::env::args();
-    let _program_name = args.next();
     while let Some(arg) = args.next() {
         match arg.as_str() {
             "-c" | "--check" => {
@@ -133,19 +716,109 @@ fn parse_args() -> RunMode {
             "-a" | "--all" => {
                 run_all = true;
             }
+            "--dry-run" => {
+                dry_run = true;
+            }
             _ => {
                 fatal(format!("Unknown argument '{arg}'").as_str());
             }
         }
     }
 
-    if run_all {
+    let mode = if run_all {
         RunMode::All
     } else if checks.is_empty() {
         fatal("specifiying a check with --check or -c is required");
     } else {
         RunMode::Some(checks)
+    };
+
+    Command::Run { mode, dry_run }
+}
+
+fn parse_serve_args(mut args: impl Iterator<Item = String>) -> Command {
+    let mut port = 8000;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" | "--port" => {
+                port = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| fatal("--port: a port number is required"));
+            }
+            _ => fatal(format!("serve: unknown argument '{arg}'").as_str()),
+        }
     }
+
+    Command::Serve { port }
+}
+
+fn parse_incident_args(mut args: impl Iterator<Item = String>) -> IncidentCommand {
+    let Some(subcommand) = args.next() else {
+        fatal("incident: a subcommand is required, one of 'new', 'update', 'resolve', or 'list'");
+    };
+
+    let id = match subcommand.as_str() {
+        "update" | "resolve" => match args.next() {
+            Some(id) if !id.starts_with('-') => Some(id),
+            _ => fatal(format!("incident {subcommand}: an incident id is required").as_str()),
+        },
+        _ => None,
+    };
+
+    let mut title = None;
+    let mut status = None;
+    let mut severity = None;
+    let mut message = None;
+    let mut checks = vec![];
+    let mut render = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fatal(format!("{arg}: a value is required").as_str()))
+        };
+        match arg.as_str() {
+            "-t" | "--title" => title = Some(value()),
+            "-s" | "--status" => status = Some(parse_value::<IncidentStatus>(&arg, &value())),
+            "--severity" => severity = Some(parse_value::<IncidentSeverity>(&arg, &value())),
+            "-m" | "--message" => message = Some(value()),
+            "-c" | "--check" => checks.push(value()),
+            "-r" | "--render" => render = true,
+            _ => fatal(format!("Unknown argument '{arg}'").as_str()),
+        }
+    }
+
+    match (subcommand.as_str(), id) {
+        ("new", _) => IncidentCommand::New {
+            title: title.unwrap_or_else(|| fatal("incident new: --title is required")),
+            status,
+            severity,
+            message,
+            checks,
+            render,
+        },
+        ("update", Some(id)) => IncidentCommand::Update {
+            id,
+            status,
+            message: message.unwrap_or_else(|| fatal("incident update: --message is required")),
+            render,
+        },
+        ("resolve", Some(id)) => IncidentCommand::Resolve {
+            id,
+            message,
+            render,
+        },
+        ("list", _) => IncidentCommand::List,
+        _ => fatal(format!("incident: unknown subcommand '{subcommand}'").as_str()),
+    }
+}
+
+// Values are parsed the same way as the config file, so the same spellings are accepted
+fn parse_value<T: serde::de::DeserializeOwned>(arg: &str, value: &str) -> T {
+    serde_json::from_value(serde_json::Value::String(value.to_owned()))
+        .unwrap_or_else(|e| fatal(format!("{arg}: {e}").as_str()))
 }
 
 fn fatal(message: &str) -> ! {
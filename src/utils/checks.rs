@@ -1,21 +1,48 @@
 #![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
-use crate::{Check, CheckResult, CheckType};
+use crate::{Check, CheckResult, CheckType, HttpCheck, PortCheck};
 use crate::{HistoryEntry, Result, State};
 
 use super::config::update_history_section;
 
-pub async fn run_check(check: &Check) -> Result<CheckResult> {
-    let timeout = std::time::Duration::from_millis(check.timeout_ms);
+pub async fn run_check(history_dir: &str, check: &Check) -> Result<CheckResult> {
+    record_check_result(history_dir, check, timed_check(check).await)
+}
+
+// Runs the check without recording anything, a check that times out is a danger state.
+// Failures are retried as many times as the check allows
+pub async fn timed_check(check: &Check) -> Result<CheckResult> {
+    let timeout = std::time::Duration::from_millis(check.timeout_ms());
 
-    let result = tokio::time::timeout(timeout, perform_check(check)).await;
+    let mut attempt = 0;
+    loop {
+        let result = tokio::time::timeout(timeout, perform_check(check))
+            .await
+            .unwrap_or_else(|_| Ok(CheckResult::Failure(State::Danger)));
 
-    let result = result.unwrap_or_else(|_| Ok(CheckResult::Failure(State::Danger)));
+        if !matches!(result, Ok(CheckResult::Failure(_))) || attempt >= check.retries() {
+            return result;
+        }
+
+        attempt += 1;
+        println!(
+            "\t[RETRY]: {:?} failed, retrying ({attempt}/{})",
+            check.name,
+            check.retries()
+        );
+    }
+}
 
+pub fn record_check_result(
+    history_dir: &str,
+    check: &Check,
+    result: Result<CheckResult>,
+) -> Result<CheckResult> {
     match result {
         // Write to history file
         Ok(CheckResult::Success) => {
             // This function takes care of only writing additions based on some rules
             update_history_section(
+                history_dir,
                 check.name.as_str(),
                 HistoryEntry::new_today(State::Success, "No Incident".into()),
             )?;
@@ -40,45 +67,48 @@ pub async fn run_check(check: &Check) -> Result<CheckResult> {
                 }
             };
 
-            update_history_section(check.name.as_str(), history_entry)?;
+            update_history_section(history_dir, check.name.as_str(), history_entry)?;
             Ok(CheckResult::Failure(state))
         }
-        Err(_) => {
+        Ok(CheckResult::Unknown) => Ok(CheckResult::Unknown),
+        Err(err) => {
             // If there is an error with the checking program we don't want that recorded
             // as an incident in the history file
+            eprintln!("Error performing check: {err:#?}");
             Ok(CheckResult::Unknown)
         }
-        _ => unreachable!("Getting a result from a check that doesn't make sense"),
     }
 }
 
 pub async fn perform_check(check: &Check) -> Result<CheckResult> {
     let start_time = chrono::Utc::now();
-    let result = match check.check_type {
-        CheckType::Http => perform_http_check(check).await,
-        CheckType::Ping => perform_ping_check(check),
-        CheckType::Port => perform_port_check(check).await,
+    let result = match &check.check_type {
+        CheckType::Http(http) => perform_http_check(check, http).await,
+        CheckType::Ping(_) => perform_ping_check(check),
+        CheckType::Port(port) => perform_port_check(check, port).await,
     };
 
-    match result {
-        Ok(result) => {
-            println!("\t[SUCCESS]: {:?} @ {:?}", check.name, start_time,);
-            Ok(result)
-        }
-        Err(err) => {
-            eprintln!("Error performing check: {err:#?}");
-            Ok(CheckResult::Unknown)
-        }
+    if result.is_ok() {
+        println!("\t[SUCCESS]: {:?} @ {:?}", check.name, start_time);
     }
+    result
 }
 
-pub async fn perform_http_check(check: &Check) -> Result<CheckResult> {
-    let Ok(response) = reqwest::get(&check.target).await else {
+pub async fn perform_http_check(check: &Check, http: &HttpCheck) -> Result<CheckResult> {
+    let mut request = reqwest::Client::new().get(&check.target);
+    for (name, value) in &http.headers {
+        request = request.header(name, value);
+    }
+    if let Some(auth) = &http.basic_auth {
+        request = request.basic_auth(&auth.username, auth.password.as_ref());
+    }
+
+    let Ok(response) = request.send().await else {
         return Ok(CheckResult::Failure(State::Danger));
     };
 
     let status = response.status().as_u16();
-    let expected_status = check.expected_status.unwrap_or(200);
+    let expected_status = http.expected_status.unwrap_or(200);
 
     // This is the only success case
     if status == expected_status {
@@ -110,20 +140,21 @@ pub fn perform_ping_check(check: &Check) -> Result<CheckResult> {
         .arg("-c")
         .arg("1")
         .arg("-W")
-        .arg(check.timeout_ms.to_string())
+        .arg(check.timeout_ms().to_string())
         .arg(&check.target)
         .output();
 
     // There is not a well defined granularity for ping checks
-    // and no support to specify them (yet?)
+    // and no support to specify them (yet?). Not being able to run ping at all is a
+    // problem on this machine, not with the target
     match output {
         Ok(_) => Ok(CheckResult::Success),
-        Err(_) => Ok(CheckResult::Failure(State::Danger)),
+        Err(e) => Err(format!("could not run ping: {e}").into()),
     }
 }
 
-pub async fn perform_port_check(check: &Check) -> Result<CheckResult> {
-    let target = format!("{}:{}", check.target, check.port.unwrap());
+pub async fn perform_port_check(check: &Check, port: &PortCheck) -> Result<CheckResult> {
+    let target = format!("{}:{}", check.target, port.port);
     let output = tokio::net::TcpStream::connect(&target).await;
 
     // There is not a well defined granularity for port checks
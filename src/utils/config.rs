@@ -1,35 +1,353 @@
 #![allow(clippy::missing_errors_doc)]
 use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use std::path::{Path, PathBuf};
+
+use crate::utils::interpolate::resolve_placeholders;
 use crate::Result;
-use crate::{long_date_format, HistoryEntry, State, StatusPageContext, HISTORY_PATH};
+use crate::{
+    long_date_format, Check, HistoryEntry, Incident, State, StatusPageContext, ThemeParams,
+    CHECKS_DIR, CONFIG_EXTENSIONS, CONFIG_PATH, DATE_FORMAT, INCIDENTS_DIR,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HistoryFile {
     pub watchers: Vec<HistorySection>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone)]
 pub struct HistorySection {
     pub name: String,
     #[serde(with = "long_date_format")]
+    #[schemars(with = "String")]
     pub last_updated: NaiveDateTime,
     pub uptime: Option<f64>,
     pub entries: Vec<HistoryEntry>,
 }
 
-pub fn read_config_file(config_path: &str) -> Result<StatusPageContext> {
+// The config is used in two forms. The checks run with every placeholder resolved, while
+// the page and the history use the config as it is written, so a resolved secret never
+// ends up in either. The build settings are never shown, so both use the resolved ones
+pub struct LoadedConfig {
+    pub written: StatusPageContext,
+    pub resolved: StatusPageContext,
+}
+
+pub fn read_config_file(config_path: &str) -> Result<LoadedConfig> {
     let config_file = std::fs::read_to_string(config_path)?;
-    let config: StatusPageContext = serde_json::from_str(&config_file)?;
-    Ok(config)
+    let mut config: StatusPageContext = parse_config(config_path, &config_file)?;
+
+    // Checks from the checks directory are added after the ones in the config file
+    config
+        .checks
+        .extend(read_checks_files(&checks_path(config_path))?);
+    apply_defaults(&mut config);
+    config.settings.apply_fallbacks();
+
+    if let Some((path, message)) = theme_problems(&config.settings.theme).first() {
+        return Err(format!("settings.theme.{}: {message}", path.join(".")).into());
+    }
+
+    // Incident files are added after the inline incidents
+    config
+        .incidents
+        .extend(read_incident_files(&incidents_path(config_path))?);
+
+    let resolved = resolve_placeholders(&config)?;
+    config.settings.build = resolved.settings.build.clone();
+
+    Ok(LoadedConfig {
+        written: config,
+        resolved,
+    })
+}
+
+// Theme values are written into the page's CSS as they are, so they can't end the
+// declaration or the style element they are in
+#[must_use]
+pub fn theme_problems(theme: &ThemeParams) -> Vec<(Vec<&'static str>, String)> {
+    theme
+        .values()
+        .into_iter()
+        .filter(|(_, value)| value.contains([';', '{', '}', '<', '>']))
+        .map(|(path, value)| {
+            let message = format!(
+                "'{value}' is not a valid CSS value, it can't contain ';', '{{', '}}', '<' or '>'"
+            );
+            (path, message)
+        })
+        .collect()
+}
+
+// Uses the first config file that exists out of `CONFIG_PATH` with each of the
+// supported extensions, so `config/config.yaml` is found without any flags
+#[must_use]
+pub fn find_config_file() -> String {
+    let default_path = Path::new(CONFIG_PATH);
+
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|extension| default_path.with_extension(extension))
+        .find(|path| path.is_file())
+        .map_or_else(|| CONFIG_PATH.to_owned(), |path| path.display().to_string())
+}
+
+// The format is picked from the file extension. The errors of every format include
+// the line and column of the problem
+pub fn parse_config<T: DeserializeOwned>(config_path: &str, contents: &str) -> Result<T> {
+    let extension = Path::new(config_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+
+    match extension {
+        "json" => Ok(serde_json::from_str(contents)?),
+        "toml" => Ok(toml::from_str(contents)?),
+        "yaml" | "yml" => Ok(serde_yaml::from_str(contents)?),
+        _ => Err(format!(
+            "unsupported config file extension '.{extension}', expected one of: {}",
+            CONFIG_EXTENSIONS.join(", ")
+        )
+        .into()),
+    }
+}
+
+// Fills in the settings a check leaves out, from its group and then the global defaults
+pub fn apply_defaults(config: &mut StatusPageContext) {
+    for check in &mut config.checks {
+        let group = check
+            .group
+            .as_ref()
+            .and_then(|name| config.groups.iter().find(|group| &group.name == name));
+
+        if let Some(group) = group {
+            check.apply_defaults(&group.defaults);
+        }
+        check.apply_defaults(&config.defaults);
+    }
+}
+
+fn config_dir(config_path: &str) -> &Path {
+    Path::new(config_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+}
+
+// Incident files live in a directory next to the config file
+#[must_use]
+pub fn incidents_path(config_path: &str) -> PathBuf {
+    config_dir(config_path).join(INCIDENTS_DIR)
+}
+
+// So does the checks directory
+#[must_use]
+pub fn checks_path(config_path: &str) -> PathBuf {
+    config_dir(config_path).join(CHECKS_DIR)
+}
+
+// A file in the checks directory has a `checks` list, in any of the config formats
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksFile {
+    pub checks: Vec<Check>,
+}
+
+// Each team can keep their checks in their own file, files are read in order of their names
+pub fn read_checks_files(checks_path: &Path) -> Result<Vec<Check>> {
+    let mut checks = vec![];
+    for path in checks_file_paths(checks_path)? {
+        checks.extend(read_checks_file(&path)?);
+    }
+    Ok(checks)
 }
 
-pub fn create_history_file(check_name: &str) -> Result<HistorySection> {
-    // Turn a "Backend API" into "Backend_API"
+pub fn checks_file_paths(checks_path: &Path) -> Result<Vec<PathBuf>> {
+    if !checks_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = std::fs::read_dir(checks_path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| CONFIG_EXTENSIONS.contains(&extension))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    Ok(paths)
+}
+
+pub fn read_checks_file(checks_file_path: &Path) -> Result<Vec<Check>> {
+    let path = checks_file_path.display().to_string();
+    let contents = std::fs::read_to_string(checks_file_path)?;
+
+    let checks_file: ChecksFile =
+        parse_config(&path, &contents).map_err(|e| format!("{path}: {e}"))?;
+    Ok(checks_file.checks)
+}
+
+// Each incident is a Markdown file, where the front matter holds the incident fields
+// and the body is the description. Files are read in order of their names
+pub fn read_incident_files(incidents_path: &Path) -> Result<Vec<Incident>> {
+    incident_file_paths(incidents_path)?
+        .iter()
+        .map(|path| read_incident_file(path))
+        .collect()
+}
+
+pub fn incident_file_paths(incidents_path: &Path) -> Result<Vec<PathBuf>> {
+    if !incidents_path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut paths = std::fs::read_dir(incidents_path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "md"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    Ok(paths)
+}
+
+pub fn read_incident_file(incident_path: &Path) -> Result<Incident> {
+    let incident_file = std::fs::read_to_string(incident_path)?;
+    parse_incident_file(incident_path, &incident_file)
+}
+
+fn parse_incident_file(incident_path: &Path, incident_file: &str) -> Result<Incident> {
+    let Some((front_matter, body)) = split_front_matter(incident_file) else {
+        return Err(format!(
+            "{}: missing front matter, expected it between '---' (YAML) or '+++' (TOML) lines",
+            incident_path.display()
+        )
+        .into());
+    };
+
+    let incident: std::result::Result<Incident, String> = match front_matter {
+        FrontMatter::Yaml(yaml) => serde_yaml::from_str(yaml).map_err(|e| e.to_string()),
+        FrontMatter::Toml(toml) => toml::from_str(toml).map_err(|e| e.to_string()),
+    };
+    let mut incident = incident.map_err(|e| format!("{}: {e}", incident_path.display()))?;
+
+    let body = body.trim();
+    if !body.is_empty() {
+        incident.description = Some(body.to_owned());
+    }
+
+    incident.id = incident_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+
+    Ok(incident)
+}
+
+// Incident files are always written with YAML front matter. The file is checked to
+// be readable before it replaces the old one, and is swapped in with a rename so a
+// failed write never leaves a half written incident behind
+pub fn write_incident_file(incident_path: &Path, incident: &Incident) -> Result<()> {
+    let mut front_matter = incident.clone();
+    let description = front_matter.description.take().unwrap_or_default();
+    front_matter.id = None;
+
+    let yaml = serde_yaml::to_string(&front_matter)?;
+    let incident_file = format!("---\n{yaml}---\n\n{}\n", description.trim());
+
+    parse_incident_file(incident_path, &incident_file)?;
+
+    let temp_path = incident_path.with_extension("md.tmp");
+    std::fs::write(&temp_path, incident_file)?;
+    std::fs::rename(&temp_path, incident_path)?;
+    Ok(())
+}
+
+// New incident files are named after the date they started and their title,
+// e.g. "2024-11-02-slow-dashboard-loading.md"
+pub fn create_incident_file(incidents_path: &Path, incident: &Incident) -> Result<PathBuf> {
+    std::fs::create_dir_all(incidents_path)?;
+
+    let base_name = format!(
+        "{}-{}",
+        incident.started_at.format(DATE_FORMAT),
+        slugify(&incident.title)
+    );
+
+    let mut incident_path = incidents_path.join(format!("{base_name}.md"));
+    let mut suffix = 2;
+    while incident_path.exists() {
+        incident_path = incidents_path.join(format!("{base_name}-{suffix}.md"));
+        suffix += 1;
+    }
+
+    write_incident_file(&incident_path, incident)?;
+    Ok(incident_path)
+}
+
+// Turn "Backend API Outage!" into "backend-api-outage". Text without any ASCII letters
+// or digits, like "!!!" or "障害", becomes "incident" so file and page names are never empty
+#[must_use]
+pub fn slugify(text: &str) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+
+    if slug.is_empty() {
+        String::from("incident")
+    } else {
+        slug
+    }
+}
+
+pub enum FrontMatter<'a> {
+    Yaml(&'a str),
+    Toml(&'a str),
+}
+
+// Front matter is the block at the very start of a file between two '---' or '+++' lines
+#[must_use]
+pub fn split_front_matter(contents: &str) -> Option<(FrontMatter<'_>, &str)> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let first_line_end = contents.find('\n')?;
+    let delimiter = contents[..first_line_end].trim_end();
+
+    if delimiter != "---" && delimiter != "+++" {
+        return None;
+    }
+
+    let rest = &contents[first_line_end + 1..];
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            let front_matter = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+
+            return match delimiter {
+                "---" => Some((FrontMatter::Yaml(front_matter), body)),
+                _ => Some((FrontMatter::Toml(front_matter), body)),
+            };
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+// Turn a "Backend API" into "<history_dir>/Backend_API_history.json"
+fn history_file_path(history_dir: &str, check_name: &str) -> PathBuf {
     let check_file_path = check_name.split(' ').collect::<Vec<&str>>().join("_");
 
-    let file_path = format!("{HISTORY_PATH}/{check_file_path}_history.json");
+    Path::new(history_dir).join(format!("{check_file_path}_history.json"))
+}
+
+pub fn create_history_file(history_dir: &str, check_name: &str) -> Result<HistorySection> {
+    let file_path = history_file_path(history_dir, check_name);
 
     let history = HistorySection {
         name: check_name.into(),
@@ -39,54 +357,44 @@ pub fn create_history_file(check_name: &str) -> Result<HistorySection> {
     };
 
     let history_json = serde_json::to_string_pretty(&history)?;
+    std::fs::create_dir_all(history_dir)?;
     std::fs::write(file_path, history_json)?;
     Ok(history)
 }
 
-pub fn does_history_file_exist(check_name: &str) -> Result<bool> {
-    // Turn a "Backend API" into "Backend_API"
-    let check_file_path = check_name.split(' ').collect::<Vec<&str>>().join("_");
-
-    let file_path = format!("{HISTORY_PATH}/{check_file_path}_history.json");
-
-    Ok(std::path::Path::new(&file_path).exists())
+pub fn does_history_file_exist(history_dir: &str, check_name: &str) -> Result<bool> {
+    Ok(history_file_path(history_dir, check_name).exists())
 }
 
-pub fn read_history_file(check_name: &str) -> Result<HistorySection> {
-    // Turn a "Backend API" into "Backend_API"
-    let check_file_path = check_name.split(' ').collect::<Vec<&str>>().join("_");
-
-    let file_path = format!("{HISTORY_PATH}/{check_file_path}_history.json");
-
-    let history_file = std::fs::read_to_string(file_path)?;
+pub fn read_history_file(history_dir: &str, check_name: &str) -> Result<HistorySection> {
+    let history_file = std::fs::read_to_string(history_file_path(history_dir, check_name))?;
     let history: HistorySection = serde_json::from_str(&history_file)?;
     Ok(history)
 }
 
-pub fn write_history_file(check_name: &str, history: &HistorySection) -> Result<()> {
-    // Turn a "Backend API" into "Backend_API"
-    let check_file_path = check_name.split(' ').collect::<Vec<&str>>().join("_");
-
-    let file_path = format!("{HISTORY_PATH}/{check_file_path}_history.json");
-
+pub fn write_history_file(
+    history_dir: &str,
+    check_name: &str,
+    history: &HistorySection,
+) -> Result<()> {
     let history_json = serde_json::to_string_pretty(&history)?;
-    std::fs::write(file_path, history_json)?;
+    std::fs::write(history_file_path(history_dir, check_name), history_json)?;
     Ok(())
 }
 
 // History is stored so the newest entry is at the end of the array
-pub fn append_history_event(section: &str, event: HistoryEntry) -> Result<()> {
+pub fn append_history_event(history_dir: &str, section: &str, event: HistoryEntry) -> Result<()> {
     // We want to mutate to add because a copy could be really expensive
-    let mut history = read_history_file(section)?;
+    let mut history = read_history_file(history_dir, section)?;
 
     history.entries.push(event);
 
-    write_history_file(section, &history)?;
+    write_history_file(history_dir, section, &history)?;
     Ok(())
 }
 
-pub fn update_history_section(section: &str, event: HistoryEntry) -> Result<()> {
-    let mut history = read_history_file(section)?;
+pub fn update_history_section(history_dir: &str, section: &str, event: HistoryEntry) -> Result<()> {
+    let mut history = read_history_file(history_dir, section)?;
     history.last_updated = chrono::Utc::now().naive_utc();
 
     match history.entries.last() {
@@ -128,8 +436,216 @@ pub fn update_history_section(section: &str, event: HistoryEntry) -> Result<()>
                 history.entries.push(event);
             }
         }
-    };
+    }
 
-    write_history_file(section, &history)?;
+    write_history_file(history_dir, section, &history)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckType;
+
+    fn split(contents: &str) -> Option<(&str, &str, &str)> {
+        split_front_matter(contents).map(|(front_matter, body)| match front_matter {
+            FrontMatter::Yaml(yaml) => ("yaml", yaml, body),
+            FrontMatter::Toml(toml) => ("toml", toml, body),
+        })
+    }
+
+    #[test]
+    fn splits_yaml_and_toml_front_matter() {
+        assert_eq!(
+            split("---\ntitle: Outage\n---\n\nThe body\n"),
+            Some(("yaml", "title: Outage\n", "\nThe body\n"))
+        );
+        assert_eq!(
+            split("+++\r\ntitle = \"Outage\"\r\n+++\r\nThe body"),
+            Some(("toml", "title = \"Outage\"\r\n", "The body"))
+        );
+    }
+
+    #[test]
+    fn splits_front_matter_after_a_byte_order_mark() {
+        assert_eq!(
+            split("\u{feff}---\ntitle: Outage\n---"),
+            Some(("yaml", "title: Outage\n", ""))
+        );
+    }
+
+    #[test]
+    fn only_ends_front_matter_at_a_matching_line() {
+        // A `---` inside the YAML or the body does not end it early
+        assert_eq!(
+            split("---\ntitle: a --- b\n+++\n---\nbody\n---\n"),
+            Some(("yaml", "title: a --- b\n+++\n", "body\n---\n"))
+        );
+    }
+
+    #[test]
+    fn rejects_files_without_front_matter() {
+        assert_eq!(split("Just a description"), None);
+        assert_eq!(split("---\ntitle: never closed\n"), None);
+        assert_eq!(split("\n---\ntitle: Outage\n---\n"), None);
+        assert_eq!(split("---"), None);
+    }
+
+    #[test]
+    fn slugifies_titles() {
+        assert_eq!(slugify("Backend API Outage!"), "backend-api-outage");
+        assert_eq!(slugify("  Slow -- dashboard (EU) "), "slow-dashboard-eu");
+        assert_eq!(slugify("Database (EU-West) 2"), "database-eu-west-2");
+    }
+
+    #[test]
+    fn slugifies_titles_without_ascii_to_a_fallback() {
+        assert_eq!(slugify("障害"), "incident");
+        assert_eq!(slugify("!!!"), "incident");
+        assert_eq!(slugify(""), "incident");
+    }
+
+    const JSON_CONFIG: &str = r#"{
+        "settings": { "site": { "name": "Example" } },
+        "checks": [{ "name": "API", "type": "http", "target": "https://example.com" }],
+        "incidents": []
+    }"#;
+
+    const TOML_CONFIG: &str = r#"
+        incidents = []
+
+        [settings.site]
+        name = "Example"
+
+        [[checks]]
+        name = "API"
+        type = "http"
+        target = "https://example.com"
+    "#;
+
+    const YAML_CONFIG: &str = "
+settings:
+  site:
+    name: Example
+checks:
+  - name: API
+    type: http
+    target: https://example.com
+incidents: []
+";
+
+    #[test]
+    fn parses_every_config_format_the_same() {
+        let json: serde_json::Value = parse_config("config.json", JSON_CONFIG).unwrap();
+        let toml: serde_json::Value = parse_config("config.toml", TOML_CONFIG).unwrap();
+        let yaml: serde_json::Value = parse_config("config.yaml", YAML_CONFIG).unwrap();
+        let yml: serde_json::Value = parse_config("config.yml", YAML_CONFIG).unwrap();
+
+        assert_eq!(json, toml);
+        assert_eq!(json, yaml);
+        assert_eq!(json, yml);
+
+        let config: StatusPageContext = parse_config("config.toml", TOML_CONFIG).unwrap();
+        assert_eq!(config.checks[0].name, "API");
+    }
+
+    #[test]
+    fn reports_unsupported_config_extensions() {
+        let error = parse_config::<StatusPageContext>("config.ini", JSON_CONFIG)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'.ini'"), "{error}");
+
+        assert!(parse_config::<StatusPageContext>("config", JSON_CONFIG).is_err());
+    }
+
+    #[test]
+    fn reports_the_line_of_a_config_error() {
+        let yaml = YAML_CONFIG.replace("type: http", "type: carrier-pigeon");
+        let error = parse_config::<StatusPageContext>("config.yaml", &yaml)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 6"), "{error}");
+    }
+
+    #[test]
+    fn reads_the_old_ongoing_status_as_investigating() {
+        for status in ["ongoing", "Ongoing", "investigating"] {
+            let status: crate::IncidentStatus =
+                serde_json::from_value(serde_json::json!(status)).unwrap();
+            assert_eq!(status, crate::IncidentStatus::Investigating);
+        }
+    }
+
+    #[test]
+    fn applies_group_defaults_before_global_defaults() {
+        let mut config: StatusPageContext = serde_json::from_value(serde_json::json!({
+            "settings": {},
+            "defaults": {
+                "timeout_ms": 1000,
+                "retries": 1,
+                "expected_status": 204,
+                "headers": { "User-Agent": "global", "X-Global": "yes" },
+            },
+            "groups": [{
+                "name": "Backend",
+                "defaults": { "timeout_ms": 2000, "headers": { "User-Agent": "group" } },
+            }],
+            "checks": [
+                {
+                    "name": "Own settings",
+                    "group": "Backend",
+                    "type": "http",
+                    "target": "https://example.com",
+                    "timeout_ms": 3000,
+                    "expected_status": 200,
+                    "headers": { "User-Agent": "check" },
+                },
+                { "name": "Grouped", "group": "Backend", "type": "http", "target": "https://example.com" },
+                { "name": "Ungrouped", "type": "ping", "target": "example.com" },
+            ],
+            "incidents": [],
+        }))
+        .unwrap();
+
+        apply_defaults(&mut config);
+
+        let http = |check: &Check| match &check.check_type {
+            CheckType::Http(http) => http.clone(),
+            _ => panic!("expected an http check"),
+        };
+
+        let own = &config.checks[0];
+        assert_eq!(own.timeout_ms(), 3000);
+        assert_eq!(own.retries(), 1);
+        assert_eq!(http(own).expected_status, Some(200));
+        assert_eq!(http(own).headers["User-Agent"], "check");
+        assert_eq!(http(own).headers["X-Global"], "yes");
+
+        let grouped = &config.checks[1];
+        assert_eq!(grouped.timeout_ms(), 2000);
+        assert_eq!(http(grouped).expected_status, Some(204));
+        assert_eq!(http(grouped).headers["User-Agent"], "group");
+
+        let ungrouped = &config.checks[2];
+        assert_eq!(ungrouped.timeout_ms(), 1000);
+        assert_eq!(ungrouped.retries(), 1);
+    }
+
+    #[test]
+    fn reports_theme_values_that_break_out_of_the_css() {
+        let theme: ThemeParams = serde_json::from_value(serde_json::json!({
+            "font_family": "Inter, sans-serif",
+            "colors": { "link": "rgb(13 71 161)" },
+            "dark": { "text": "red; } body { display: none" },
+            "light": { "background": "</style><script>" },
+        }))
+        .unwrap();
+
+        let paths = theme_problems(&theme)
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["light.background", "dark.text"]);
+    }
+}
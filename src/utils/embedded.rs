@@ -0,0 +1,332 @@
+#![allow(clippy::missing_errors_doc)]
+use sha2::{Digest, Sha256};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::minify::minify;
+use crate::utils::output::write_built_file;
+use crate::{BuildParams, Result, ASSETS_PATH, TEMPLATES_PATH};
+
+// The default templates and assets are compiled into the binary, so it can be run from
+// anywhere without the source tree next to it. A theme directory can replace any of them
+pub const TEMPLATES: &[(&str, &str)] = &[
+    (
+        "base.html.jinja",
+        include_str!("../templates/base.html.jinja"),
+    ),
+    (
+        "feed.xml.jinja",
+        include_str!("../templates/feed.xml.jinja"),
+    ),
+    (
+        "index.html.jinja",
+        include_str!("../templates/index.html.jinja"),
+    ),
+    (
+        "incident.html.jinja",
+        include_str!("../templates/incident.html.jinja"),
+    ),
+    (
+        "incidents.html.jinja",
+        include_str!("../templates/incidents.html.jinja"),
+    ),
+    (
+        "partials/active_incidents.html.jinja",
+        include_str!("../templates/partials/active_incidents.html.jinja"),
+    ),
+    (
+        "partials/group.html.jinja",
+        include_str!("../templates/partials/group.html.jinja"),
+    ),
+    (
+        "partials/history_entry.html.jinja",
+        include_str!("../templates/partials/history_entry.html.jinja"),
+    ),
+    (
+        "partials/incident.html.jinja",
+        include_str!("../templates/partials/incident.html.jinja"),
+    ),
+    (
+        "partials/overall.html.jinja",
+        include_str!("../templates/partials/overall.html.jinja"),
+    ),
+    (
+        "partials/page_header.html.jinja",
+        include_str!("../templates/partials/page_header.html.jinja"),
+    ),
+    (
+        "partials/status.html.jinja",
+        include_str!("../templates/partials/status.html.jinja"),
+    ),
+];
+
+pub const ASSETS: &[(&str, &[u8])] = &[
+    ("style.css", include_bytes!("../../assets/style.css")),
+    (
+        "theme-toggle.js",
+        include_bytes!("../../assets/theme-toggle.js"),
+    ),
+    (
+        "CommitMono.woff2",
+        include_bytes!("../../assets/CommitMono.woff2"),
+    ),
+    (
+        "nanowatchrs_logo.svg",
+        include_bytes!("../../assets/nanowatchrs_logo.svg"),
+    ),
+    (
+        "nanowatchrs_full_logo.svg",
+        include_bytes!("../../assets/nanowatchrs_full_logo.svg"),
+    ),
+    (
+        "nanowatchrs_text.svg",
+        include_bytes!("../../assets/nanowatchrs_text.svg"),
+    ),
+];
+
+// Templates in the theme directory take priority over the built in ones
+pub fn load_template(build: &BuildParams, name: &str) -> Result<Option<String>> {
+    let theme_templates = build
+        .theme_dir
+        .as_ref()
+        .map(|theme_dir| Path::new(theme_dir).join("templates"));
+    let source_templates = build.from_source.then(|| PathBuf::from(TEMPLATES_PATH));
+
+    for templates_dir in theme_templates.into_iter().chain(source_templates) {
+        let template_path = templates_dir.join(name);
+        if template_path.is_file() {
+            return Ok(Some(fs::read_to_string(template_path)?));
+        }
+    }
+
+    Ok(TEMPLATES
+        .iter()
+        .find(|(template_name, _)| *template_name == name)
+        .map(|(_, template)| (*template).to_owned()))
+}
+
+// Maps each asset's name to the fingerprinted file name it was written as
+pub type AssetManifest = HashMap<String, String>;
+
+// Assets that are renamed after a hash of their content, so they can be cached forever
+const FINGERPRINTED_EXTENSIONS: &[&str] = &["css", "js", "woff2"];
+
+// Writes the built in assets to the output directory, replacing any that are overridden
+// by the theme. Extra files in the theme's assets directory are copied over as well.
+// Stylesheets, scripts and fonts get a content hash in their file name, and references
+// between them are rewritten, so a new build never serves stale files from a cache
+pub fn write_static_assets(build: &BuildParams) -> Result<AssetManifest> {
+    let mut assets: BTreeMap<String, Vec<u8>> = ASSETS
+        .iter()
+        .map(|(name, content)| ((*name).to_owned(), content.to_vec()))
+        .collect();
+
+    if build.from_source {
+        for (name, content) in &mut assets {
+            let source_path = Path::new(ASSETS_PATH).join(name);
+            if source_path.is_file() {
+                *content = fs::read(source_path)?;
+            }
+        }
+    }
+
+    let theme_assets = build
+        .theme_dir
+        .as_ref()
+        .map(|theme_dir| Path::new(theme_dir).join("assets"));
+    if let Some(theme_assets) = theme_assets.filter(|dir| dir.is_dir()) {
+        for entry in fs::read_dir(theme_assets)? {
+            let path = entry?.path();
+            if let Some(name) = path.file_name().filter(|_| path.is_file()) {
+                assets.insert(name.to_string_lossy().into_owned(), fs::read(&path)?);
+            }
+        }
+    }
+
+    let mut manifest = AssetManifest::new();
+
+    // Stylesheets reference the other assets, so they are hashed after rewriting
+    let (stylesheets, others): (Vec<_>, Vec<_>) = assets
+        .into_iter()
+        .partition(|(name, _)| is_stylesheet(name));
+
+    for (name, mut content) in others.into_iter().chain(stylesheets) {
+        // Minified before hashing, so turning minification on changes the file name
+        if build.minify {
+            content = minify(&name, content)?;
+        }
+
+        if is_stylesheet(&name) {
+            let css = String::from_utf8(content)?;
+            content = rewrite_stylesheet_urls(&css, &manifest).into_bytes();
+        }
+
+        let file_name = match split_extension(&name) {
+            Some((stem, extension)) if FINGERPRINTED_EXTENSIONS.contains(&extension) => {
+                let file_name = format!("{stem}.{}.{extension}", content_hash(&content));
+                remove_stale_fingerprints(build.output_dir(), stem, extension, &file_name)?;
+                file_name
+            }
+            _ => name.clone(),
+        };
+
+        write_built_file(build, &file_name, &content)?;
+        manifest.insert(name, file_name);
+    }
+
+    Ok(manifest)
+}
+
+fn is_stylesheet(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("css"))
+}
+
+// Points the `url(...)` references of a stylesheet at the fingerprinted files. Only a
+// reference to the whole name is replaced, so `font.woff2` is left alone inside
+// `my-font.woff2`. Quotes and a query or fragment after the name are kept
+fn rewrite_stylesheet_urls(css: &str, manifest: &AssetManifest) -> String {
+    let lowercase = css.to_ascii_lowercase();
+    let mut rewritten = String::with_capacity(css.len());
+    let mut copied = 0;
+
+    for (start, _) in lowercase.match_indices("url(") {
+        // Part of a longer name, like `myurl(`
+        let is_function = !css[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let argument_start = start + "url(".len();
+        let Some(length) = css[argument_start..].find(')') else {
+            break;
+        };
+        if !is_function || argument_start < copied {
+            continue;
+        }
+
+        let argument_end = argument_start + length;
+        rewritten.push_str(&css[copied..argument_start]);
+        rewritten.push_str(&rewrite_url(&css[argument_start..argument_end], manifest));
+        copied = argument_end;
+    }
+
+    rewritten.push_str(&css[copied..]);
+    rewritten
+}
+
+fn rewrite_url(argument: &str, manifest: &AssetManifest) -> String {
+    let trimmed = argument.trim();
+    let (quote, url) = match trimmed.chars().next() {
+        Some(quote @ ('"' | '\'')) if trimmed.len() > 1 && trimmed.ends_with(quote) => {
+            (trimmed[..1].to_owned(), &trimmed[1..trimmed.len() - 1])
+        }
+        _ => (String::new(), trimmed),
+    };
+
+    // Assets are written to the root of the output, so a leading `/` or `./` is kept
+    let prefix = ["./", "/"]
+        .into_iter()
+        .find(|prefix| url.starts_with(prefix))
+        .unwrap_or_default();
+    let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    match manifest.get(&path[prefix.len()..]) {
+        Some(fingerprinted) => format!("{quote}{prefix}{fingerprinted}{suffix}{quote}"),
+        None => argument.to_owned(),
+    }
+}
+
+fn split_extension(name: &str) -> Option<(&str, &str)> {
+    name.rsplit_once('.')
+}
+
+// The first 8 bytes of the SHA-256 digest, which is plenty to tell builds apart
+fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .take(8)
+        .fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+}
+
+// Removes the files left behind by earlier builds of the same asset, along with their
+// compressed siblings, so the output directory does not grow with every change
+fn remove_stale_fingerprints(
+    output_dir: &str,
+    stem: &str,
+    extension: &str,
+    current: &str,
+) -> Result<()> {
+    let Ok(entries) = fs::read_dir(output_dir) else {
+        return Ok(());
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let base_name = file_name
+            .strip_suffix(".gz")
+            .or_else(|| file_name.strip_suffix(".br"))
+            .unwrap_or(file_name);
+
+        let is_fingerprint = base_name
+            .strip_prefix(stem)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|rest| rest.strip_suffix(extension))
+            .and_then(|rest| rest.strip_suffix('.'))
+            .is_some_and(|hash| hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()));
+
+        if is_fingerprint && base_name != current {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> AssetManifest {
+        [
+            ("font.woff2", "font.0123456789abcdef.woff2"),
+            ("style.css", "style.fedcba9876543210.css"),
+        ]
+        .into_iter()
+        .map(|(name, fingerprinted)| (name.to_owned(), fingerprinted.to_owned()))
+        .collect()
+    }
+
+    #[test]
+    fn rewrites_whole_url_references() {
+        let css = r#"@font-face { src: url("font.woff2") format("woff2"), url('./font.woff2?v=2#x'), URL( /font.woff2 ); }"#;
+
+        assert_eq!(
+            rewrite_stylesheet_urls(css, &manifest()),
+            r#"@font-face { src: url("font.0123456789abcdef.woff2") format("woff2"), url('./font.0123456789abcdef.woff2?v=2#x'), URL(/font.0123456789abcdef.woff2); }"#
+        );
+    }
+
+    #[test]
+    fn leaves_names_that_only_contain_an_asset_name() {
+        let css = "a { background: url(my-font.woff2); } b { content: 'font.woff2'; } c { x: myurl(font.woff2); } @import url(other/style.css);";
+
+        assert_eq!(rewrite_stylesheet_urls(css, &manifest()), css);
+    }
+
+    #[test]
+    fn keeps_an_unterminated_url() {
+        let css = "a { background: url(font.woff2";
+
+        assert_eq!(rewrite_stylesheet_urls(css, &manifest()), css);
+    }
+}
@@ -0,0 +1,270 @@
+#![allow(clippy::missing_errors_doc)]
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Result, StatusPageContext};
+
+// Replaces `${NAME}` with the environment variable `NAME`, and `${file:/path}` with the
+// contents of the file, without a trailing newline. `$${` is a literal `${`
+pub fn interpolate(text: &str) -> Result<String> {
+    let mut interpolated = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        interpolated.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            interpolated.push_str("${");
+            rest = escaped;
+        } else if let Some(placeholder) = rest.strip_prefix("${") {
+            let Some(end) = placeholder.find('}') else {
+                return Err(
+                    format!("unterminated placeholder in '{text}', expected a '}}'").into(),
+                );
+            };
+            interpolated.push_str(&resolve(&placeholder[..end])?);
+            rest = &placeholder[end + 1..];
+        } else {
+            interpolated.push('$');
+            rest = &rest[1..];
+        }
+    }
+
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+fn resolve(placeholder: &str) -> Result<String> {
+    if let Some(path) = placeholder.strip_prefix("file:") {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read '{path}' for '${{{placeholder}}}': {e}"))?;
+        return Ok(contents.trim_end_matches(['\r', '\n']).to_owned());
+    }
+
+    if placeholder.is_empty() {
+        return Err("empty placeholder '${}', expected a variable name".into());
+    }
+
+    std::env::var(placeholder).map_err(|e| match e {
+        std::env::VarError::NotPresent => {
+            format!("environment variable '{placeholder}' is not set").into()
+        }
+        std::env::VarError::NotUnicode(_) => {
+            format!("environment variable '{placeholder}' is not valid unicode").into()
+        }
+    })
+}
+
+#[must_use]
+pub fn has_placeholder(text: &str) -> bool {
+    text.replace("$${", "").contains("${")
+}
+
+// Every string in the config can have placeholders, which are resolved in a copy of it.
+// Check names are used for the history files and the page, so they can't have any
+pub fn resolve_placeholders(config: &StatusPageContext) -> Result<StatusPageContext> {
+    if let Some(check) = config
+        .checks
+        .iter()
+        .find(|check| has_placeholder(&check.name))
+    {
+        return Err(format!(
+            "check '{}': names can not contain placeholders, they are shown on the page",
+            check.name
+        )
+        .into());
+    }
+
+    let mut value = serde_json::to_value(config)?;
+    interpolate_value(&mut value, &mut String::new())?;
+    Ok(serde_json::from_value(value)?)
+}
+
+// The placeholders in a part of the config that can't be resolved, by their path in it
+pub fn unresolved_placeholders<T: Serialize>(part: &T) -> Vec<(String, String)> {
+    let mut problems = vec![];
+    if let Ok(value) = serde_json::to_value(part) {
+        collect_unresolved(&value, &mut String::new(), &mut problems);
+    }
+    problems
+}
+
+fn interpolate_value(value: &mut Value, path: &mut String) -> Result<()> {
+    match value {
+        Value::String(text) => {
+            *text = interpolate(text).map_err(|e| format!("{path}: {e}"))?;
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                with_segment(path, &format!("[{i}]"), |path| {
+                    interpolate_value(value, path)
+                })?;
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                with_segment(path, key, |path| interpolate_value(value, path))?;
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+
+    Ok(())
+}
+
+fn collect_unresolved(value: &Value, path: &mut String, problems: &mut Vec<(String, String)>) {
+    match value {
+        Value::String(text) => {
+            if let Err(e) = interpolate(text) {
+                problems.push((path.clone(), e.to_string()));
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                with_segment(path, &format!("[{i}]"), |path| {
+                    collect_unresolved(value, path, problems);
+                });
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                with_segment(path, key, |path| collect_unresolved(value, path, problems));
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+// Paths look like `checks[0].headers.Authorization`
+fn with_segment<T>(path: &mut String, segment: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let length = path.len();
+    if !path.is_empty() && !segment.starts_with('[') {
+        path.push('.');
+    }
+    path.push_str(segment);
+    let result = f(path);
+    path.truncate(length);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CheckType;
+
+    fn config(checks: serde_json::Value) -> StatusPageContext {
+        serde_json::from_value(serde_json::json!({
+            "settings": { "site": { "name": "Status ${NANOWATCHRS_TEST_SITE}" } },
+            "checks": checks,
+            "incidents": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn interpolates_variables_and_escapes() {
+        std::env::set_var("NANOWATCHRS_TEST_TOKEN", "secret");
+
+        assert_eq!(
+            interpolate("Bearer ${NANOWATCHRS_TEST_TOKEN}").unwrap(),
+            "Bearer secret"
+        );
+        assert_eq!(
+            interpolate("$${HOME} costs $5").unwrap(),
+            "${HOME} costs $5"
+        );
+        assert!(interpolate("${NANOWATCHRS_TEST_UNSET}").is_err());
+        assert!(interpolate("${NANOWATCHRS_TEST_TOKEN").is_err());
+        assert!(interpolate("${}").is_err());
+    }
+
+    #[test]
+    fn interpolates_files_without_the_trailing_newline() {
+        let path = std::env::temp_dir().join("nanowatchrs_interpolate_test_password");
+        std::fs::write(&path, "hunter2\n").unwrap();
+
+        let text = format!("${{file:{}}}", path.display());
+        assert_eq!(interpolate(&text).unwrap(), "hunter2");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resolves_every_string_in_a_copy() {
+        std::env::set_var("NANOWATCHRS_TEST_HOST", "api.example.com");
+        std::env::set_var("NANOWATCHRS_TEST_USER", "status");
+        std::env::set_var("NANOWATCHRS_TEST_SITE", "Example");
+
+        let mut config = config(serde_json::json!([{
+            "name": "API",
+            "description": "Calls ${NANOWATCHRS_TEST_HOST}",
+            "type": "http",
+            "target": "https://${NANOWATCHRS_TEST_HOST}/health",
+            "headers": { "Host": "${NANOWATCHRS_TEST_HOST}" },
+            "basic_auth": { "username": "${NANOWATCHRS_TEST_USER}" },
+        }]));
+        config.settings.build.output_dir = Some("/srv/${NANOWATCHRS_TEST_USER}".to_owned());
+        let resolved = resolve_placeholders(&config).unwrap();
+
+        let check = &resolved.checks[0];
+        assert_eq!(check.target, "https://api.example.com/health");
+        assert_eq!(check.description.as_deref(), Some("Calls api.example.com"));
+        let CheckType::Http(http) = &check.check_type else {
+            panic!("expected an http check");
+        };
+        assert_eq!(http.headers["Host"], "api.example.com");
+        assert_eq!(http.basic_auth.as_ref().unwrap().username, "status");
+        assert_eq!(
+            resolved.settings.site.name.as_deref(),
+            Some("Status Example")
+        );
+        assert_eq!(resolved.settings.build.output_dir(), "/srv/status");
+
+        // The original is left as it was written
+        assert_eq!(
+            config.checks[0].target,
+            "https://${NANOWATCHRS_TEST_HOST}/health"
+        );
+        assert_eq!(
+            config.settings.site.name.as_deref(),
+            Some("Status ${NANOWATCHRS_TEST_SITE}")
+        );
+    }
+
+    #[test]
+    fn rejects_placeholders_in_check_names() {
+        let config = config(serde_json::json!([{
+            "name": "API ${NANOWATCHRS_TEST_HOST}",
+            "type": "ping",
+            "target": "example.com",
+        }]));
+
+        let error = resolve_placeholders(&config).unwrap_err().to_string();
+        assert!(
+            error.contains("names can not contain placeholders"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn reports_the_path_of_a_missing_variable() {
+        let config = config(serde_json::json!([{
+            "name": "Backend",
+            "type": "http",
+            "target": "https://example.com",
+            "headers": { "Authorization": "${NANOWATCHRS_TEST_MISSING}" },
+        }]));
+
+        let error = resolve_placeholders(&config).unwrap_err().to_string();
+        assert!(
+            error.starts_with("checks[0].headers.Authorization: "),
+            "{error}"
+        );
+        assert!(error.contains("NANOWATCHRS_TEST_MISSING"), "{error}");
+
+        let problems = unresolved_placeholders(&config.checks[0]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, "headers.Authorization");
+    }
+}
@@ -0,0 +1,268 @@
+#![allow(clippy::missing_errors_doc)]
+use crate::Result;
+
+// Elements whose content is whitespace sensitive or not HTML, and is copied as is
+const RAW_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+// Minifies HTML and CSS files, anything else is returned unchanged
+pub fn minify(file_name: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+    let minified = match file_name.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("html") => minify_html(&String::from_utf8(content)?),
+        Some("css") => minify_css(&String::from_utf8(content)?),
+        _ => return Ok(content),
+    };
+
+    Ok(minified.into_bytes())
+}
+
+// Collapses whitespace between and inside of tags and removes comments. Runs of
+// whitespace become a single space rather than nothing, since between inline
+// elements the space is visible. Attribute values are left untouched
+#[must_use]
+pub fn minify_html(html: &str) -> String {
+    let mut minified = String::with_capacity(html.len());
+    let mut rest = html.trim();
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            // Drop the comment, or everything if it is never closed
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        if c == '<' {
+            let tag_end = tag_length(rest);
+            minified.push_str(&collapse_tag(&rest[..tag_end]));
+
+            // The content of raw elements is copied up to the closing tag
+            let raw_element = RAW_ELEMENTS
+                .iter()
+                .find(|name| opens_element(&rest[..tag_end], name));
+            rest = &rest[tag_end..];
+
+            if let Some(name) = raw_element {
+                let content_end = find_closing_tag(rest, name).unwrap_or(rest.len());
+                minified.push_str(&rest[..content_end]);
+                rest = &rest[content_end..];
+            }
+            continue;
+        }
+
+        if c.is_ascii_whitespace() {
+            rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+            // The whitespace on both sides of a removed comment is one run
+            if !minified.ends_with(' ') {
+                minified.push(' ');
+            }
+            continue;
+        }
+
+        minified.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    minified
+}
+
+// The length of the tag at the start of `html`, including the closing `>`. Quoted
+// attribute values can contain `>`, so they are skipped over
+fn tag_length(html: &str) -> usize {
+    let mut quote = None;
+
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+
+    html.len()
+}
+
+// Collapses the whitespace between attributes, leaving quoted values as they are
+fn collapse_tag(tag: &str) -> String {
+    let mut collapsed = String::with_capacity(tag.len());
+    let mut quote = None;
+    let mut pending_space = false;
+
+    for c in tag.chars() {
+        if quote.is_none() && c.is_ascii_whitespace() {
+            pending_space = true;
+            continue;
+        }
+
+        if pending_space && !matches!(c, '>' | '/') {
+            collapsed.push(' ');
+        }
+        pending_space = false;
+
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            _ => {}
+        }
+        collapsed.push(c);
+    }
+
+    collapsed
+}
+
+fn opens_element(tag: &str, name: &str) -> bool {
+    tag.get(1..=name.len())
+        .is_some_and(|tag_name| tag_name.eq_ignore_ascii_case(name))
+        && tag[name.len() + 1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c == '>' || c == '/' || c.is_ascii_whitespace())
+}
+
+fn find_closing_tag(html: &str, name: &str) -> Option<usize> {
+    let closing_tag = format!("</{name}");
+    html.to_ascii_lowercase().find(&closing_tag)
+}
+
+// Removes comments and the whitespace around punctuation. Spaces before a `:` and
+// around `+`, `-` and `~` are kept, since they are meaningful in selectors and `calc()`
+#[must_use]
+pub fn minify_css(css: &str) -> String {
+    let mut minified = String::with_capacity(css.len());
+    let mut rest = css.trim();
+    let mut pending_space = false;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.find("*/").map_or("", |end| &comment[end + 2..]);
+            continue;
+        }
+
+        if c.is_ascii_whitespace() {
+            pending_space = true;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let trims_space = matches!(c, '{' | '}' | ';' | ',' | '>' | ')');
+        let last = minified.chars().last();
+        if pending_space
+            && !trims_space
+            && !matches!(last, None | Some('{' | '}' | ';' | ',' | ':' | '>' | '('))
+        {
+            minified.push(' ');
+        }
+        pending_space = false;
+
+        if c == '"' || c == '\'' {
+            // Strings are copied as is, including the closing quote
+            let end = rest[1..].find(c).map_or(rest.len(), |end| end + 2);
+            minified.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+
+        // The last declaration in a block does not need a semicolon
+        if c == '}' && minified.ends_with(';') {
+            minified.pop();
+        }
+
+        minified.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    minified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_whitespace_and_removes_comments() {
+        let html = "\n<div   class=\"a  b\"\n  id='x' >\n  <!-- note -->\n  <span>one</span>\n  <b>two</b>\n</div>\n";
+
+        assert_eq!(
+            minify_html(html),
+            "<div class=\"a  b\" id='x'> <span>one</span> <b>two</b> </div>"
+        );
+    }
+
+    #[test]
+    fn keeps_the_content_of_pre_and_textarea() {
+        let html =
+            "<pre>  line one\n    line two  </pre>\n<textarea name=\"t\">\n  keep  me\n</textarea>";
+
+        assert_eq!(
+            minify_html(html),
+            "<pre>  line one\n    line two  </pre> <textarea name=\"t\">\n  keep  me\n</textarea>"
+        );
+    }
+
+    #[test]
+    fn keeps_inline_scripts_and_styles() {
+        let html = "<script>\n  if (a < b  &&  c > d) { x(\"<!-- y -->\"); }\n</script>\n<STYLE media=\"all\">\n  a  >  b { color: red; }\n</STYLE>";
+
+        assert_eq!(
+            minify_html(html),
+            "<script>\n  if (a < b  &&  c > d) { x(\"<!-- y -->\"); }\n</script> <STYLE media=\"all\">\n  a  >  b { color: red; }\n</STYLE>"
+        );
+    }
+
+    #[test]
+    fn does_not_treat_similar_tags_as_raw() {
+        let html = "<preview>  a  </preview><scripts>  b  </scripts>";
+
+        assert_eq!(
+            minify_html(html),
+            "<preview> a </preview><scripts> b </scripts>"
+        );
+    }
+
+    #[test]
+    fn keeps_attribute_values_with_angle_brackets() {
+        let html = "<a title=\"1 > 0\"   href=\"#\">  x  </a>";
+
+        assert_eq!(minify_html(html), "<a title=\"1 > 0\" href=\"#\"> x </a>");
+    }
+
+    #[test]
+    fn minifies_css() {
+        let css = "/* header */\n.a  .b ,\n.c > .d {\n  color : red ;\n  margin: 0 auto;\n}\n\na :hover { top: 0 }\n";
+
+        assert_eq!(
+            minify_css(css),
+            ".a .b,.c>.d{color :red;margin:0 auto}a :hover{top:0}"
+        );
+    }
+
+    #[test]
+    fn keeps_css_strings() {
+        let css = ".a::before { content: \"  /* not a comment */ ; { } \"; font-family: 'Commit  Mono', monospace; }";
+
+        assert_eq!(
+            minify_css(css),
+            ".a::before{content:\"  /* not a comment */ ; { } \";font-family:'Commit  Mono',monospace}"
+        );
+    }
+
+    #[test]
+    fn keeps_the_spaces_calc_needs() {
+        let css = ".a { width: calc( 100% - ( 2 * 1rem ) ); height: calc(1px + -2px); }";
+
+        assert_eq!(
+            minify_css(css),
+            ".a{width:calc(100% - (2 * 1rem));height:calc(1px + -2px)}"
+        );
+    }
+
+    #[test]
+    fn only_minifies_html_and_css_files() {
+        let script = b"let  a =  1;\n".to_vec();
+
+        assert_eq!(minify("app.js", script.clone()).unwrap(), script);
+        assert_eq!(
+            minify("page.html", b"<p>  a  </p>".to_vec()).unwrap(),
+            b"<p> a </p>"
+        );
+    }
+}
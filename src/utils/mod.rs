@@ -1,3 +1,10 @@
 pub mod checks;
 pub mod config;
+pub mod embedded;
+pub mod interpolate;
+pub mod minify;
+pub mod output;
+pub mod schema;
+pub mod serve;
 pub mod templates;
+pub mod validate;
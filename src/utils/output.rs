@@ -0,0 +1,80 @@
+#![allow(clippy::missing_errors_doc)]
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::utils::minify::minify;
+use crate::{BuildParams, Result};
+
+// Text outputs that get `.gz` and `.br` siblings when precompression is enabled
+const COMPRESSED_EXTENSIONS: &[&str] = &["html", "css", "js", "json"];
+
+// Writes to a temporary file next to the destination and renames it into place, so
+// anything serving the output directory never sees a partially written file
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("'{}' is not a file path", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// File names are relative to the output directory, and can include subdirectories
+pub fn write_output_file(build: &BuildParams, file_name: &str, content: &[u8]) -> Result<()> {
+    if build.minify {
+        write_built_file(build, file_name, &minify(file_name, content.to_vec())?)
+    } else {
+        write_built_file(build, file_name, content)
+    }
+}
+
+// Writes the file as it is, along with its compressed copies when enabled
+pub fn write_built_file(build: &BuildParams, file_name: &str, content: &[u8]) -> Result<()> {
+    let path = Path::new(build.output_dir()).join(file_name);
+    write_atomic(&path, content)?;
+
+    let compressible = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| COMPRESSED_EXTENSIONS.contains(&extension));
+
+    if build.precompress() && compressible {
+        write_atomic(&compressed_path(&path, "gz"), &gzip(content)?)?;
+        write_atomic(&compressed_path(&path, "br"), &brotli(content)?)?;
+    }
+
+    Ok(())
+}
+
+#[must_use]
+pub fn compressed_path(path: &Path, extension: &str) -> std::path::PathBuf {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".");
+    compressed.push(extension);
+    compressed.into()
+}
+
+fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+fn brotli(content: &[u8]) -> Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        encoder.write_all(content)?;
+    }
+    Ok(compressed)
+}
@@ -0,0 +1,36 @@
+#![allow(clippy::missing_errors_doc)]
+use schemars::{schema_for, Schema};
+
+use crate::utils::config::{ChecksFile, HistorySection};
+use crate::{Result, StatusPageContext};
+
+// The files that have a schema, generated from the types they are read into so it can
+// never be out of date
+#[derive(Debug, Clone, Copy)]
+pub enum SchemaKind {
+    Config,
+    Checks,
+    History,
+}
+
+impl SchemaKind {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "config" => Some(Self::Config),
+            "checks" => Some(Self::Checks),
+            "history" => Some(Self::History),
+            _ => None,
+        }
+    }
+}
+
+pub fn json_schema(kind: SchemaKind) -> Result<String> {
+    let schema: Schema = match kind {
+        SchemaKind::Config => schema_for!(StatusPageContext),
+        SchemaKind::Checks => schema_for!(ChecksFile),
+        SchemaKind::History => schema_for!(HistorySection),
+    };
+
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
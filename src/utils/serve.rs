@@ -0,0 +1,259 @@
+#![allow(clippy::missing_errors_doc)]
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Pages poll this to find out when the site has been rendered again
+const VERSION_PATH: &str = "/__nanowatchrs/version";
+
+// Added to every HTML page that is served, reloads the page after a new render
+const RELOAD_SCRIPT: &str = r"<script>
+(() => {
+  let version;
+  setInterval(async () => {
+    const current = await fetch('/__nanowatchrs/version').then((r) => r.text()).catch(() => version);
+    if (version !== undefined && current !== version) location.reload();
+    version = current;
+  }, 1000);
+})();
+</script>";
+
+// Serves the files in the output directory until the process is stopped. The
+// version is bumped by whoever renders the site, which reloads any open pages
+pub async fn serve_directory(listener: TcpListener, root: PathBuf, version: Arc<AtomicUsize>) {
+    loop {
+        match listener.accept().await {
+            Err(e) => eprintln!("Error accepting connection: '{e:#?}'"),
+            Ok((stream, _)) => {
+                let root = root.clone();
+                let version = version.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &root, &version).await {
+                        eprintln!("Error handling request: '{e:#?}'");
+                    }
+                });
+            }
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    version: &AtomicUsize,
+) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+
+    // Only the request line is needed, but the headers are read so the client is not cut off
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+
+    let (status, content_type, body) = if method != "GET" && method != "HEAD" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method Not Allowed".to_vec(),
+        )
+    } else if target == VERSION_PATH {
+        let version = version.load(Ordering::SeqCst).to_string();
+        ("200 OK", "text/plain", version.into_bytes())
+    } else {
+        match resolve_path(root, target).and_then(|path| Some((fs::read(&path).ok()?, path))) {
+            None => ("404 Not Found", "text/plain", b"Not Found".to_vec()),
+            Some((content, path)) => {
+                let content_type = content_type(&path);
+                if content_type.starts_with("text/html") {
+                    ("200 OK", content_type, inject_reload_script(&content))
+                } else {
+                    ("200 OK", content_type, content)
+                }
+            }
+        }
+    };
+
+    if target != VERSION_PATH {
+        println!("{method} {target} {status}");
+    }
+
+    let headers = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(headers.as_bytes()).await?;
+    if method != "HEAD" {
+        stream.write_all(&body).await?;
+    }
+    stream.shutdown().await
+}
+
+// Maps a request path onto a file in the root, directories are served by their index.html.
+// Paths that would leave the root are rejected
+fn resolve_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path)?;
+
+    let relative = Path::new(path.trim_start_matches('/'));
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let path = root.join(relative);
+    if path.is_dir() {
+        Some(path.join("index.html"))
+    } else {
+        Some(path)
+    }
+}
+
+fn percent_decode(path: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            // Checked first, as `from_str_radix` also accepts a sign
+            let hex = [bytes.next()?, bytes.next()?];
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("xml") => "application/atom+xml",
+        Some("svg") => "image/svg+xml",
+        Some("woff2") => "font/woff2",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+fn inject_reload_script(content: &[u8]) -> Vec<u8> {
+    let html = String::from_utf8_lossy(content);
+    match html.rfind("</html>") {
+        Some(end) => format!("{}{RELOAD_SCRIPT}{}", &html[..end], &html[end..]).into_bytes(),
+        None => format!("{html}{RELOAD_SCRIPT}").into_bytes(),
+    }
+}
+
+// The newest modification time and the number of files under the given paths, any
+// change to either means something was edited, added or removed. Anything inside
+// `ignore` is skipped, so writing the output does not look like a change
+#[must_use]
+pub fn watch_stamp(paths: &[PathBuf], ignore: &Path) -> (Option<SystemTime>, usize) {
+    let mut latest = None;
+    let mut count = 0;
+    let mut pending = paths.to_vec();
+
+    while let Some(path) = pending.pop() {
+        if path.starts_with(ignore) {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(&path) {
+                pending.extend(entries.filter_map(|entry| Some(entry.ok()?.path())));
+            }
+        } else {
+            count += 1;
+            latest = latest.max(metadata.modified().ok());
+        }
+    }
+
+    (latest, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("/a%20b%2Fc").as_deref(), Some("/a b/c"));
+        assert_eq!(percent_decode("/caf%C3%A9").as_deref(), Some("/café"));
+
+        assert_eq!(percent_decode("/100%"), None);
+        assert_eq!(percent_decode("/%4"), None);
+        assert_eq!(percent_decode("/%zz"), None);
+        assert_eq!(percent_decode("/%+1"), None);
+        assert_eq!(percent_decode("/%FF"), None);
+    }
+
+    #[test]
+    fn resolves_paths_inside_the_root() {
+        let root = std::env::temp_dir().join("nanowatchrs_serve_test_root");
+        std::fs::create_dir_all(root.join("incidents")).unwrap();
+
+        assert_eq!(resolve_path(&root, "/"), Some(root.join("index.html")));
+        assert_eq!(
+            resolve_path(&root, "/incidents"),
+            Some(root.join("incidents/index.html"))
+        );
+        assert_eq!(
+            resolve_path(&root, "/style.css?v=1#top"),
+            Some(root.join("style.css"))
+        );
+        assert_eq!(
+            resolve_path(&root, "/feed.xml#latest"),
+            Some(root.join("feed.xml"))
+        );
+        assert_eq!(
+            resolve_path(&root, "/my%20page.html"),
+            Some(root.join("my page.html"))
+        );
+
+        // A leading slash, or several, never makes the path absolute
+        assert_eq!(
+            resolve_path(&root, "//etc/passwd"),
+            Some(root.join("etc/passwd"))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_that_leave_the_root() {
+        let root = Path::new("/srv/public");
+
+        assert_eq!(resolve_path(root, "/../secret"), None);
+        assert_eq!(resolve_path(root, "/incidents/../../secret"), None);
+        assert_eq!(resolve_path(root, "/%2e%2e/secret"), None);
+        assert_eq!(resolve_path(root, "/%2E%2E%2Fsecret"), None);
+        assert_eq!(resolve_path(root, "/./index.html"), None);
+        assert_eq!(resolve_path(root, "/%zz"), None);
+    }
+}
@@ -1,13 +1,17 @@
 #![allow(clippy::missing_errors_doc, clippy::option_if_let_else)]
 use chrono::{NaiveDateTime, Utc};
-use minijinja::{context, path_loader, Environment};
+use minijinja::{context, Environment, ErrorKind, Value};
+use pulldown_cmark::{Event, Options, Parser};
 
-use std::fs;
+use std::collections::HashSet;
+use std::fmt::Write;
 
 use crate::utils::config::HistorySection;
+use crate::utils::embedded::{load_template, AssetManifest};
 use crate::{
-    Check, HistoryEntry, Incident, Result, State, ASSETS_PATH, DATE_FORMAT, HISTORY_LENGTH,
-    LONG_DATE_FORMAT, TIME_FORMAT,
+    BuildParams, Check, Group, HistoryEntry, Impact, Incident, IncidentUpdate, Result, State,
+    StatusPageContext, ThemePalette, ThemeParams, DATE_FORMAT, FEED_DATE_FORMAT, HISTORY_LENGTH,
+    INCIDENTS_PER_PAGE, LONG_DATE_FORMAT, TIME_FORMAT,
 };
 
 fn date(date_str: &str) -> String {
@@ -24,30 +28,48 @@ fn time(date_str: &str) -> String {
     }
 }
 
-pub fn create_env<'a>() -> Environment<'a> {
+// The built in `escape` filter also escapes slashes, which is not needed for XML
+fn xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub fn create_env<'a>(build: &BuildParams, assets: AssetManifest) -> Environment<'a> {
     let mut env = Environment::new();
-    // Load the templates from the src/templates directory
-    env.set_loader(path_loader("src/templates"));
+    // Load the templates built into the binary, unless the theme overrides them
+    let build = build.clone();
+    env.set_loader(move |name| {
+        load_template(&build, name).map_err(|e| {
+            minijinja::Error::new(
+                ErrorKind::InvalidOperation,
+                format!("could not read template '{name}': {e}"),
+            )
+        })
+    });
 
     // Add custom filters
     env.add_filter("date", date);
     env.add_filter("time", time);
+    env.add_filter("xml", xml);
+    env.add_filter("markdown", |markdown: &str| {
+        Value::from_safe_string(render_markdown(markdown))
+    });
 
-    env
-}
+    // Resolves an asset to the fingerprinted file name it was written as
+    env.add_function("asset", move |name: &str| {
+        assets.get(name).cloned().unwrap_or_else(|| name.to_owned())
+    });
 
-pub fn write_string_to_asset_folder(file_name: &str, content: &str) -> Result<()> {
-    let full_path = format!("{ASSETS_PATH}/{file_name}");
-    fs::write(full_path, content)?;
-    Ok(())
+    env
 }
 
-// Block name comes from the "checks" in the config file
-pub fn render_status_block(
-    env: &Environment<'_>,
-    check: &Check,
-    history_section: &HistorySection,
-) -> Result<String> {
+// The history line is the last `HISTORY_LENGTH` days, oldest first, with any
+// missing days filled in as disabled. Also returns the uptime over those days
+pub fn build_history_line(history_section: &HistorySection) -> (Vec<HistoryEntry>, f64) {
     #[allow(clippy::cast_possible_wrap)]
     let date_cutoff = Utc::now().naive_utc() - chrono::Duration::days((HISTORY_LENGTH) as i64);
 
@@ -88,6 +110,121 @@ pub fn render_status_block(
 
     let history = history.into_iter().rev().collect::<Vec<HistoryEntry>>();
 
+    (history, uptime)
+}
+
+// The current state of a check is the state of the most recent day
+#[must_use]
+pub fn current_state(history_section: &HistorySection) -> State {
+    let (history, _) = build_history_line(history_section);
+
+    match history.last() {
+        Some(entry) => entry.state.clone(),
+        None => State::Disabled,
+    }
+}
+
+// Combines the states of several checks into one, based on the impact of each check.
+// The most severe state wins, and disabled states only count if nothing else is known
+pub fn aggregate_state<'a>(states: impl IntoIterator<Item = (&'a Impact, &'a State)>) -> State {
+    states
+        .into_iter()
+        .filter_map(|(impact, state)| match impact {
+            Impact::Informational if state.severity() > State::Warning.severity() => {
+                Some(State::Warning)
+            }
+            Impact::Critical | Impact::Informational => Some(state.clone()),
+            Impact::None => None,
+        })
+        .max_by_key(State::severity)
+        .unwrap_or(State::Disabled)
+}
+
+// The banner at the top of the page summarizing every check
+pub fn render_overall_status(
+    env: &Environment<'_>,
+    checks: &[(&Check, HistorySection)],
+) -> Result<String> {
+    let states = checks
+        .iter()
+        .map(|(check, history_section)| (&check.impact, current_state(history_section)))
+        .collect::<Vec<(&Impact, State)>>();
+
+    let state = aggregate_state(states.iter().map(|(impact, state)| (*impact, state)));
+
+    let context = context! {
+        status => state.to_overall_status(),
+        state => state.to_state(),
+    };
+
+    let template = env.get_template("partials/overall.html.jinja")?;
+
+    let rendered = template.render(context);
+
+    match rendered {
+        Ok(rendered) => Ok(rendered),
+        Err(e) => {
+            eprintln!("Template Render Error: {e:#?}");
+            Err(e.into())
+        }
+    }
+}
+
+// Groups are rendered as a collapsible section holding the status blocks of their checks
+pub fn render_group(
+    env: &Environment<'_>,
+    group: &Group,
+    checks: &[(&Check, HistorySection)],
+    incidents: &[Incident],
+) -> Result<String> {
+    // The roll-up for a group shows the worst state of its checks, the impact
+    // of a check only changes how it counts towards the overall status
+    let states = checks
+        .iter()
+        .map(|(_, history_section)| current_state(history_section))
+        .collect::<Vec<State>>();
+
+    let state = aggregate_state(states.iter().map(|state| (&Impact::Critical, state)));
+
+    let status_blocks = checks
+        .iter()
+        .filter_map(|(check, history_section)| {
+            render_status_block(env, check, history_section, incidents).ok()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let context = context! {
+        title => group.display_name.as_ref().unwrap_or(&group.name),
+        description => group.description,
+        collapsed => group.collapsed,
+        status => state.to_status(),
+        state => state.to_state(),
+        rendered_blocks => status_blocks,
+    };
+
+    let template = env.get_template("partials/group.html.jinja")?;
+
+    let rendered = template.render(context);
+
+    match rendered {
+        Ok(rendered) => Ok(rendered),
+        Err(e) => {
+            eprintln!("Template Render Error: {e:#?}");
+            Err(e.into())
+        }
+    }
+}
+
+// Block name comes from the "checks" in the config file
+pub fn render_status_block(
+    env: &Environment<'_>,
+    check: &Check,
+    history_section: &HistorySection,
+    incidents: &[Incident],
+) -> Result<String> {
+    let (history, uptime) = build_history_line(history_section);
+
     let state = match history.last() {
         Some(entry) => entry.state.clone(),
         None => State::Disabled,
@@ -95,6 +232,44 @@ pub fn render_status_block(
 
     let display_status = state.to_status();
 
+    let incidents = incidents
+        .iter()
+        .filter(|incident| incident.affects(&check.name))
+        .collect::<Vec<&Incident>>();
+
+    // Each day on the history line lists the incidents that were happening on it
+    let history = history
+        .into_iter()
+        .map(|entry| {
+            let day_incidents = incidents
+                .iter()
+                .filter(|incident| incident.covers_date(entry.date))
+                .map(|incident| incident.title.clone())
+                .collect::<Vec<String>>();
+
+            context! {
+                date => entry.date.format(DATE_FORMAT).to_string(),
+                state => entry.state,
+                notes => entry.notes,
+                incidents => day_incidents,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let now = Utc::now().naive_utc();
+    let active_incidents = incidents
+        .iter()
+        .filter(|incident| incident.is_active(now))
+        .map(|incident| {
+            let status = incident.current_status();
+            context! {
+                title => incident.title,
+                status => status.map(|status| status.to_status()),
+                state => status.map(|status| incident.state_for(status).to_state().to_owned()),
+            }
+        })
+        .collect::<Vec<_>>();
+
     let context = context! {
         title => check.name,
         subtitle => check.description,
@@ -104,6 +279,7 @@ pub fn render_status_block(
         updated_at => history_section.last_updated.format(LONG_DATE_FORMAT).to_string(),
         uptime => format!("{:.02}", uptime),
         history_line => history,
+        active_incidents => active_incidents,
     };
 
     let template = env.get_template("partials/status.html.jinja")?;
@@ -119,23 +295,281 @@ pub fn render_status_block(
     }
 }
 
-// Incidents are defined as text in the config.json file
-pub fn render_incident(env: &Environment<'_>, incident: &Incident) -> Result<String> {
-    let state = match incident.status.as_str() {
-        "Resolved" | "resolved" => "success",
-        "Ongoing" | "ongoing" => "warning",
-        _ => "",
+// Every incident gets its own page, named after its id or its start date and title.
+// Names are made unique with a number suffix, handed out oldest first so a new incident
+// never changes the link of an existing one. Incidents are returned newest first
+#[must_use]
+pub fn incident_permalinks(incidents: &[Incident]) -> Vec<(String, &Incident)> {
+    let mut incidents = incidents.iter().collect::<Vec<&Incident>>();
+    incidents.sort_by_key(|incident| incident.started_at);
+
+    let mut seen = HashSet::new();
+    let mut permalinks = incidents
+        .into_iter()
+        .map(|incident| {
+            let base_slug = incident.slug();
+            let mut slug = base_slug.clone();
+            let mut suffix = 2;
+            while !seen.insert(slug.clone()) {
+                slug = format!("{base_slug}-{suffix}");
+                suffix += 1;
+            }
+            (slug, incident)
+        })
+        .collect::<Vec<_>>();
+
+    permalinks.sort_by_key(|(_, incident)| std::cmp::Reverse(incident.started_at));
+    permalinks
+}
+
+// The archive is every incident split into pages, grouped by the month they started in,
+// along with a page for each incident. Returns the path and contents of each page
+pub fn render_incident_archive(
+    env: &Environment<'_>,
+    config: &StatusPageContext,
+    permalinks: &[(String, &Incident)],
+) -> Result<Vec<(String, String)>> {
+    // Archive pages live in the incidents directory, one level below the main page
+    let root = "../";
+    let mut pages = vec![];
+
+    let incident_template = env.get_template("incident.html.jinja")?;
+    for (slug, incident) in permalinks {
+        let context = context! {
+            site => config.settings.site,
+            page => config.settings.page,
+            root => root,
+            canonical => config.settings.site.canonical_link(&format!("incidents/{slug}.html")),
+            title => incident.title,
+            incident => render_incident(env, incident, &format!("{slug}.html"))?,
+        };
+
+        pages.push((
+            format!("incidents/{slug}.html"),
+            incident_template.render(context)?,
+        ));
+    }
+
+    let archive_template = env.get_template("incidents.html.jinja")?;
+    let chunks = permalinks.chunks(INCIDENTS_PER_PAGE).collect::<Vec<_>>();
+    let total_pages = chunks.len().max(1);
+
+    for page_number in 1..=total_pages {
+        let page_incidents = chunks.get(page_number - 1).copied().unwrap_or_default();
+
+        let mut months: Vec<(String, Vec<String>)> = vec![];
+        for (slug, incident) in page_incidents {
+            let month = incident.started_at.format("%B %Y").to_string();
+            let rendered = render_incident(env, incident, &format!("{slug}.html"))?;
+
+            match months.last_mut() {
+                Some((last_month, incidents)) if *last_month == month => incidents.push(rendered),
+                _ => months.push((month, vec![rendered])),
+            }
+        }
+
+        let context = context! {
+            site => config.settings.site,
+            page => config.settings.page,
+            root => root,
+            canonical => config
+                .settings
+                .site
+                .canonical_link(&format!("incidents/{}", archive_page_name(page_number))),
+            months => months
+                .into_iter()
+                .map(|(name, incidents)| context! { name => name, incidents => incidents })
+                .collect::<Vec<_>>(),
+            page_number => page_number,
+            total_pages => total_pages,
+            newer_link => (page_number > 1).then(|| archive_page_name(page_number - 1)),
+            older_link => (page_number < total_pages).then(|| archive_page_name(page_number + 1)),
+        };
+
+        pages.push((
+            format!("incidents/{}", archive_page_name(page_number)),
+            archive_template.render(context)?,
+        ));
+    }
+
+    Ok(pages)
+}
+
+// An Atom feed of every incident, so visitors can subscribe to updates
+pub fn render_incident_feed(
+    env: &Environment<'_>,
+    config: &StatusPageContext,
+    permalinks: &[(String, &Incident)],
+) -> Result<String> {
+    let site_url = config.settings.site.public_url();
+
+    let last_updated = |incident: &Incident| {
+        incident
+            .updates
+            .iter()
+            .map(|update| update.posted_at)
+            .chain(incident.resolved_at)
+            .chain(std::iter::once(incident.started_at))
+            .max()
+            .unwrap_or(incident.started_at)
     };
-    // TODO: status enum for color
-    // TODO: longer form text, WYSIWYG? Markdown?
+
+    let entries = permalinks
+        .iter()
+        .map(|(slug, incident)| {
+            let mut updates = incident.updates.iter().collect::<Vec<&IncidentUpdate>>();
+            updates.sort_by_key(|update| std::cmp::Reverse(update.posted_at));
+
+            let content = incident
+                .description
+                .iter()
+                .map(|description| render_markdown(description))
+                .chain(updates.iter().map(|update| {
+                    format!(
+                        "<p><strong>{}</strong> {}</p>\n{}",
+                        update.status.to_status(),
+                        update.posted_at.format(LONG_DATE_FORMAT),
+                        render_markdown(&update.message)
+                    )
+                }))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            context! {
+                title => incident.title,
+                url => format!("{site_url}/incidents/{slug}.html"),
+                status => incident.current_status().map(|status| status.to_status()),
+                updated => last_updated(incident).format(FEED_DATE_FORMAT).to_string(),
+                content => content,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let updated = permalinks
+        .iter()
+        .map(|(_, incident)| last_updated(incident))
+        .max()
+        .unwrap_or_else(|| Utc::now().naive_utc());
+
+    let context = context! {
+        site => config.settings.site,
+        page => config.settings.page,
+        site_url => site_url,
+        updated => updated.format(FEED_DATE_FORMAT).to_string(),
+        entries => entries,
+    };
+
+    let template = env.get_template("feed.xml.jinja")?;
+    Ok(template.render(context)?)
+}
+
+// A machine readable summary of what the page displays. Only what is already shown on
+// the page is included, so check targets and other settings never end up in it
+pub fn render_status_json(
+    config: &StatusPageContext,
+    checks: &[(&Check, HistorySection)],
+    permalinks: &[(String, &Incident)],
+) -> Result<String> {
+    let site_url = config.settings.site.public_url();
+
+    let states = checks
+        .iter()
+        .map(|(check, history_section)| (&check.impact, current_state(history_section)))
+        .collect::<Vec<(&Impact, State)>>();
+    let overall = aggregate_state(states.iter().map(|(impact, state)| (*impact, state)));
+
+    let checks = checks
+        .iter()
+        .map(|(check, history_section)| {
+            let (_, uptime) = build_history_line(history_section);
+            let state = current_state(history_section);
+            serde_json::json!({
+                "name": check.name,
+                "description": check.description,
+                "group": check.group,
+                "state": state.to_state(),
+                "status": state.to_status(),
+                "uptime": (uptime * 100.0).round() / 100.0,
+                "updated_at": history_section.last_updated.format(LONG_DATE_FORMAT).to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let incidents = permalinks
+        .iter()
+        .map(|(slug, incident)| {
+            serde_json::json!({
+                "title": incident.title,
+                "status": incident.current_status(),
+                "severity": incident.severity,
+                "started_at": incident.started_at.format(LONG_DATE_FORMAT).to_string(),
+                "resolved_at": incident
+                    .resolved_at
+                    .map(|resolved_at| resolved_at.format(LONG_DATE_FORMAT).to_string()),
+                "affected_checks": incident.affected_checks,
+                "url": format!("{site_url}/incidents/{slug}.html"),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let document = serde_json::json!({
+        "generated_at": Utc::now().naive_utc().format(LONG_DATE_FORMAT).to_string(),
+        "state": overall.to_state(),
+        "status": overall.to_overall_status(),
+        "checks": checks,
+        "incidents": incidents,
+    });
+
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+fn archive_page_name(page_number: usize) -> String {
+    if page_number == 1 {
+        String::from("index.html")
+    } else {
+        format!("page-{page_number}.html")
+    }
+}
+
+// Incidents are defined in the config.json file or in their own files
+pub fn render_incident(
+    env: &Environment<'_>,
+    incident: &Incident,
+    permalink: &str,
+) -> Result<String> {
+    let status = incident.current_status();
+
+    let mut updates = incident.updates.iter().collect::<Vec<&IncidentUpdate>>();
+    // The timeline is displayed with the newest update first
+    updates.sort_by_key(|update| std::cmp::Reverse(update.posted_at));
+
+    let updates = updates
+        .into_iter()
+        .map(|update| {
+            context! {
+                status => update.status.to_status(),
+                state => incident.state_for(update.status).to_state(),
+                message => render_markdown(&update.message),
+                posted_at => update.posted_at.format(LONG_DATE_FORMAT).to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
     let context = context! {
         title => incident.title,
-        description => format_incident_description(&incident.description),
-        state => state,
-        status => incident.status,
-        display_date => incident.display_date,
+        permalink => permalink,
+        description => incident.description.as_deref().map(render_markdown),
+        state => status.map(|status| incident.state_for(status).to_state().to_owned()),
+        status => status.map(|status| status.to_status()),
+        severity => incident.severity.to_severity(),
+        severity_state => incident.severity.to_state().to_state(),
+        updates => updates,
+        display_date => incident.display_date(),
+        duration => incident_duration(incident),
         started_at => incident.started_at.format(LONG_DATE_FORMAT).to_string(),
-        resolved_at => incident.resolved_at.format(LONG_DATE_FORMAT).to_string(),
+        resolved_at => incident
+            .resolved_at
+            .map(|resolved_at| resolved_at.format(LONG_DATE_FORMAT).to_string()),
     };
 
     let template = env.get_template("partials/incident.html.jinja")?;
@@ -151,6 +585,176 @@ pub fn render_incident(env: &Environment<'_>, incident: &Incident) -> Result<Str
     }
 }
 
-fn format_incident_description(description: &str) -> String {
-    description.split('\n').collect::<Vec<&str>>().join("<br>")
+fn incident_duration(incident: &Incident) -> String {
+    let now = Utc::now().naive_utc();
+
+    if incident.started_at > now {
+        return match incident.ended_at() {
+            Some(ended_at) => format!(
+                "Planned for {}",
+                format_duration(ended_at - incident.started_at)
+            ),
+            None => String::from("Planned"),
+        };
+    }
+
+    let duration = format_duration(incident.duration(now));
+
+    if incident.is_active(now) {
+        format!("Ongoing for {duration}")
+    } else {
+        format!("Lasted {duration}")
+    }
+}
+
+// Only the two largest units are shown, e.g. "2d 4h" or "5m 30s"
+#[must_use]
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds % 86400 / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+        (seconds % 60, "s"),
+    ];
+
+    let formatted = units
+        .iter()
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .filter(|(value, _)| *value != 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    if formatted.is_empty() {
+        String::from("0s")
+    } else {
+        formatted
+    }
+}
+
+// The theme settings as CSS that comes after the stylesheet, so it wins over the bundled
+// values. The light palette also applies before the toggle script has picked a mode
+#[must_use]
+pub fn render_theme_css(theme: &ThemeParams) -> String {
+    let mut root = vec![];
+    root.extend(
+        theme
+            .font_family
+            .as_deref()
+            .map(|value| ("--font-family", value)),
+    );
+    root.extend(
+        theme
+            .font_size
+            .as_deref()
+            .map(|value| ("--font-size", value)),
+    );
+    root.extend(theme.colors.variables());
+
+    // Buttons are highlighted with the colours of the other mode
+    let mut light = theme.light.variables();
+    light.extend(hover_variables(&theme.dark));
+    let mut dark = theme.dark.variables();
+    dark.extend(hover_variables(&theme.light));
+
+    [
+        css_rule(":root", &root),
+        css_rule(":root:not([data-theme=\"dark\"])", &light),
+        css_rule("[data-theme=\"dark\"]", &dark),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn hover_variables(palette: &ThemePalette) -> Vec<(&'static str, &str)> {
+    [
+        ("--button-background-hover", &palette.background),
+        ("--button-text-hover", &palette.text),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+    .collect()
+}
+
+fn css_rule(selector: &str, variables: &[(&str, &str)]) -> Option<String> {
+    if variables.is_empty() {
+        return None;
+    }
+
+    let declarations = variables
+        .iter()
+        .fold(String::new(), |mut declarations, (name, value)| {
+            let _ = writeln!(declarations, "  {name}: {value};");
+            declarations
+        });
+    Some(format!("{selector} {{\n{declarations}}}\n"))
+}
+
+// Incident text is written in Markdown. Single line breaks are kept as line breaks,
+// and the HTML is sanitized since it is inserted into the page as-is
+#[must_use]
+pub fn render_markdown(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+
+    let parser = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::SoftBreak => Event::HardBreak,
+        _ => event,
+    });
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+
+    ammonia::Builder::default()
+        .set_tag_attribute_value("a", "target", "_blank")
+        .clean(&html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incident(title: &str, started_at: &str) -> Incident {
+        serde_json::from_value(serde_json::json!({
+            "title": title,
+            "started_at": started_at,
+        }))
+        .unwrap()
+    }
+
+    fn slugs(incidents: &[Incident]) -> Vec<String> {
+        incident_permalinks(incidents)
+            .into_iter()
+            .map(|(slug, _)| slug)
+            .collect()
+    }
+
+    #[test]
+    fn suffixes_permalinks_oldest_first() {
+        let mut incidents = vec![
+            incident("Outage", "2024-11-02 09:00:00"),
+            incident("Outage", "2024-11-02 08:00:00"),
+        ];
+        assert_eq!(
+            slugs(&incidents),
+            ["2024-11-02-outage-2", "2024-11-02-outage"]
+        );
+
+        // A newer incident with the same name keeps the existing links as they were
+        incidents.push(incident("Outage", "2024-11-02 10:00:00"));
+        assert_eq!(
+            slugs(&incidents),
+            [
+                "2024-11-02-outage-3",
+                "2024-11-02-outage-2",
+                "2024-11-02-outage"
+            ]
+        );
+    }
 }
@@ -0,0 +1,1065 @@
+#![allow(clippy::missing_errors_doc)]
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde_json::Value;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::utils::config::{
+    checks_file_paths, checks_path, incident_file_paths, incidents_path, parse_config,
+    read_checks_file, read_incident_file, theme_problems,
+};
+use crate::utils::embedded::{load_template, AssetManifest, TEMPLATES};
+use crate::utils::interpolate::{has_placeholder, interpolate, unresolved_placeholders};
+use crate::utils::templates::create_env;
+use crate::{
+    BuildParams, Check, CheckDefaults, CheckType, Group, Incident, SiteSettings, StatusPageContext,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // A file path, with the line and column when they are known
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}: {}", self.location, self.message)
+    }
+}
+
+// Checks everything that is read when the page is built, and collects every problem
+// instead of stopping at the first one. Parsing is done element by element, so one
+// broken check does not hide the problems in the others
+#[must_use]
+pub fn validate_config(config_path: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Diagnostics {
+        path: config_path,
+        source: String::new(),
+        document: None,
+        list: vec![],
+    };
+
+    let source = match std::fs::read_to_string(config_path) {
+        Err(e) => {
+            diagnostics.error_at_file(config_path, &format!("could not be read: {e}"));
+            return diagnostics.list;
+        }
+        Ok(source) => source,
+    };
+
+    let is_json = Path::new(config_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        diagnostics.source = source;
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(&diagnostics.source) {
+            diagnostics.json_error(0, &e);
+            return diagnostics.list;
+        }
+    } else {
+        // Other formats only stop here when they can't be parsed at all, which their own
+        // library reports with the line. The rest of the checks run on the same data as
+        // JSON, and are located in the original file by their path
+        match parse_config::<Value>(config_path, &source) {
+            Err(e) => {
+                diagnostics.error_at_file(config_path, &e.to_string());
+                return diagnostics.list;
+            }
+            Ok(value) => {
+                diagnostics.source = value.to_string();
+                diagnostics.document = Some(source);
+            }
+        }
+    }
+
+    validate_config_source(&mut diagnostics, config_path);
+    diagnostics.list
+}
+
+fn validate_config_source(diagnostics: &mut Diagnostics<'_>, config_path: &str) {
+    let settings = diagnostics.parse::<SiteSettings>(&[Key::Field("settings")]);
+    diagnostics.parse::<CheckDefaults>(&[Key::Field("defaults")]);
+    let checks = diagnostics.parse_list::<Check>("checks");
+    let groups = diagnostics.parse_list::<Group>("groups");
+    let incidents = diagnostics.parse_list::<Incident>("incidents");
+
+    // Problems with the overall shape, like a missing section, are only reported when
+    // nothing more specific was found
+    if diagnostics.list.is_empty() {
+        if let Err(e) = serde_json::from_str::<StatusPageContext>(&diagnostics.source) {
+            diagnostics.json_error(0, &e);
+        }
+    }
+
+    let checks_files = read_checks_files(diagnostics, config_path);
+
+    let mut check_names = HashSet::new();
+    for (i, check) in &checks {
+        let at = |field| [Key::Field("checks"), Key::Index(*i), Key::Field(field)];
+
+        if !check_names.insert(check.name.as_str()) {
+            diagnostics.error(
+                &at("name"),
+                &format!(
+                    "duplicate check name '{}', names must be unique",
+                    check.name
+                ),
+            );
+        }
+
+        for (field, severity, message) in check_problems(check, &groups) {
+            diagnostics.push(severity, &at(field), &message);
+        }
+    }
+
+    // Checks from the checks directory are reported by their file and path
+    for (path, checks) in &checks_files {
+        for (i, check) in checks.iter().enumerate() {
+            let at = |field| format!("{}: checks[{i}].{field}", path.display());
+
+            if !check_names.insert(check.name.as_str()) {
+                diagnostics.error_at_file(
+                    &at("name"),
+                    &format!(
+                        "duplicate check name '{}', names must be unique",
+                        check.name
+                    ),
+                );
+            }
+
+            if has_placeholder(&check.name) {
+                diagnostics.error_at_file(&at("name"), "check names can not contain placeholders");
+            }
+
+            for (field, severity, message) in check_problems(check, &groups) {
+                diagnostics.list.push(Diagnostic {
+                    severity,
+                    location: at(field),
+                    message,
+                });
+            }
+            for (field, message) in unresolved_placeholders(check) {
+                let location = format!("{}: checks[{i}].{field}", path.display());
+                diagnostics.error_at_file(&location, &message);
+            }
+        }
+    }
+
+    let mut group_names = HashSet::new();
+    for (i, group) in &groups {
+        if !group_names.insert(group.name.as_str()) {
+            diagnostics.error(
+                &[Key::Field("groups"), Key::Index(*i), Key::Field("name")],
+                &format!("duplicate group name '{}'", group.name),
+            );
+        }
+    }
+
+    for (i, incident) in &incidents {
+        let path = [Key::Field("incidents"), Key::Index(*i)];
+        for message in incident_problems(incident, &check_names) {
+            diagnostics.error(&path, &message);
+        }
+    }
+
+    validate_incident_files(diagnostics, config_path, &check_names);
+
+    if let Ok(value) = serde_json::from_str::<Value>(&diagnostics.source) {
+        validate_placeholders(diagnostics, &value, &mut vec![]);
+    }
+
+    if let Some(settings) = settings {
+        for (keys, message) in theme_problems(&settings.theme) {
+            let path = [Key::Field("settings"), Key::Field("theme")]
+                .into_iter()
+                .chain(keys.into_iter().map(Key::Field))
+                .collect::<Vec<_>>();
+            diagnostics.error(&path, &message);
+        }
+
+        validate_templates(diagnostics, &settings.build);
+    }
+}
+
+// Every string is resolved when the config is loaded, where a variable or file that
+// can't be found stops the run. Check names can never have placeholders
+fn validate_placeholders<'a>(
+    diagnostics: &mut Diagnostics<'_>,
+    value: &'a Value,
+    path: &mut Vec<Key<'a>>,
+) {
+    match value {
+        Value::String(text) if has_placeholder(text) => {
+            let is_check_name = matches!(
+                path.as_slice(),
+                [Key::Field("checks"), Key::Index(_), Key::Field("name")]
+            );
+            if is_check_name {
+                diagnostics.error(path, "check names can not contain placeholders");
+            } else if let Err(e) = interpolate(text) {
+                diagnostics.error(path, &e.to_string());
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                path.push(Key::Index(i));
+                validate_placeholders(diagnostics, value, path);
+                path.pop();
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                path.push(Key::Field(key));
+                validate_placeholders(diagnostics, value, path);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+// Problems with a single check, with the field they are about
+fn check_problems(
+    check: &Check,
+    groups: &[(usize, Group)],
+) -> Vec<(&'static str, Severity, String)> {
+    let mut problems = vec![];
+
+    if matches!(check.check_type, CheckType::Http(_))
+        && !check.target.starts_with("http://")
+        && !check.target.starts_with("https://")
+    {
+        problems.push((
+            "target",
+            Severity::Error,
+            "the target of an http check must start with 'http://' or 'https://'".to_owned(),
+        ));
+    }
+
+    if let Some(group) = &check.group {
+        if !groups.iter().any(|(_, g)| &g.name == group) {
+            problems.push((
+                "group",
+                Severity::Warning,
+                format!("group '{group}' is not defined, the check is displayed ungrouped"),
+            ));
+        }
+    }
+
+    problems
+}
+
+// The files in the checks directory are parsed whole, a file that does not parse is
+// reported on its own and its checks are left out
+fn read_checks_files(
+    diagnostics: &mut Diagnostics<'_>,
+    config_path: &str,
+) -> Vec<(PathBuf, Vec<Check>)> {
+    let paths = match checks_file_paths(&checks_path(config_path)) {
+        Err(e) => {
+            let location = checks_path(config_path).display().to_string();
+            diagnostics.error_at_file(&location, &format!("could not be read: {e}"));
+            return vec![];
+        }
+        Ok(paths) => paths,
+    };
+
+    let mut checks_files = vec![];
+    for path in paths {
+        let location = path.display().to_string();
+        match read_checks_file(&path) {
+            // The error already starts with the path
+            Err(e) => diagnostics.error_at_file(
+                &location,
+                e.to_string().trim_start_matches(&format!("{location}: ")),
+            ),
+            Ok(checks) => checks_files.push((path, checks)),
+        }
+    }
+
+    checks_files
+}
+
+fn incident_problems(incident: &Incident, check_names: &HashSet<&str>) -> Vec<String> {
+    let mut problems = vec![];
+
+    for name in &incident.affected_checks {
+        if !check_names.contains(name.as_str()) {
+            problems.push(format!(
+                "incident '{}' affects '{name}', but there is no check with that name",
+                incident.title
+            ));
+        }
+    }
+
+    if incident
+        .resolved_at
+        .is_some_and(|resolved_at| resolved_at < incident.started_at)
+    {
+        problems.push(format!(
+            "incident '{}' is resolved before it started",
+            incident.title
+        ));
+    }
+
+    problems
+}
+
+fn validate_incident_files(
+    diagnostics: &mut Diagnostics<'_>,
+    config_path: &str,
+    check_names: &HashSet<&str>,
+) {
+    let paths = match incident_file_paths(&incidents_path(config_path)) {
+        Err(e) => {
+            let location = incidents_path(config_path).display().to_string();
+            diagnostics.error_at_file(&location, &format!("could not be read: {e}"));
+            return;
+        }
+        Ok(paths) => paths,
+    };
+
+    for path in paths {
+        let location = path.display().to_string();
+        match read_incident_file(&path) {
+            // The error already starts with the path
+            Err(e) => diagnostics.list.push(Diagnostic {
+                severity: Severity::Error,
+                location: location.clone(),
+                message: e
+                    .to_string()
+                    .trim_start_matches(&format!("{location}: "))
+                    .to_owned(),
+            }),
+            Ok(incident) => {
+                for message in incident_problems(&incident, check_names) {
+                    diagnostics.error_at_file(&location, &message);
+                }
+                for (field, message) in unresolved_placeholders(&incident) {
+                    diagnostics.error_at_file(&location, &format!("{field}: {message}"));
+                }
+            }
+        }
+    }
+}
+
+// Every template must compile, and everything it includes, extends or imports must exist
+fn validate_templates(diagnostics: &mut Diagnostics<'_>, build: &BuildParams) {
+    let theme_dir = build.theme_dir.as_deref();
+    let mut names: Vec<String> = TEMPLATES
+        .iter()
+        .map(|(name, _)| (*name).to_owned())
+        .collect();
+
+    if let Some(theme_dir) = theme_dir {
+        if !Path::new(theme_dir).is_dir() {
+            diagnostics.error(
+                &[
+                    Key::Field("settings"),
+                    Key::Field("build"),
+                    Key::Field("theme_dir"),
+                ],
+                &format!("the theme directory '{theme_dir}' does not exist"),
+            );
+            return;
+        }
+
+        let templates_dir = Path::new(theme_dir).join("templates");
+        for path in files_in(&templates_dir) {
+            if let Ok(name) = path.strip_prefix(&templates_dir) {
+                let name = name.to_string_lossy().replace('\\', "/");
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    let env = create_env(build, AssetManifest::new());
+    for name in &names {
+        let location = template_location(theme_dir, name);
+
+        if let Err(e) = env.get_template(name) {
+            let message = e.detail().map_or_else(|| e.to_string(), str::to_owned);
+            let location = match e.line() {
+                Some(line) => format!("{location}:{line}"),
+                None => location,
+            };
+            diagnostics.error_at_file(&location, &message);
+            continue;
+        }
+
+        let Ok(Some(source)) = load_template(build, name) else {
+            continue;
+        };
+        for (line, referenced) in referenced_templates(&source) {
+            if !matches!(load_template(build, &referenced), Ok(Some(_))) {
+                diagnostics.error_at_file(
+                    &format!("{location}:{line}"),
+                    &format!("template '{referenced}' does not exist"),
+                );
+            }
+        }
+    }
+}
+
+fn template_location(theme_dir: Option<&str>, name: &str) -> String {
+    theme_dir
+        .map(|theme_dir| Path::new(theme_dir).join("templates").join(name))
+        .filter(|path| path.is_file())
+        .map_or_else(
+            || format!("src/templates/{name} (built in)"),
+            |path| path.display().to_string(),
+        )
+}
+
+// The template names used by `include`, `extends` and `import` tags, with their line
+fn referenced_templates(source: &str) -> Vec<(usize, String)> {
+    let mut referenced = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        for tag in line.split("{%").skip(1) {
+            let tag = tag.trim_start_matches('-').trim_start();
+            let is_reference = ["include ", "extends ", "import ", "from "]
+                .iter()
+                .any(|keyword| tag.starts_with(keyword));
+
+            let name = tag
+                .split(['"', '\''])
+                .nth(1)
+                .filter(|_| is_reference && tag.contains(['"', '\'']));
+            if let Some(name) = name {
+                referenced.push((i + 1, name.to_owned()));
+            }
+        }
+    }
+
+    referenced
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+struct Diagnostics<'a> {
+    path: &'a str,
+    // Always JSON, other formats are converted to it
+    source: String,
+    // The original text of a TOML or YAML file, offsets in `source` are only positions
+    // in the file when this is not set
+    document: Option<String>,
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics<'_> {
+    fn error(&mut self, path: &[Key<'_>], message: &str) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn push(&mut self, severity: Severity, path: &[Key<'_>], message: &str) {
+        if let Some(document) = &self.document {
+            let location = (0..=path.len())
+                .rev()
+                .find_map(|len| locate_in_document(self.path, document, &path[..len]))
+                .map_or_else(
+                    || format!("{}: {}", self.path, describe_path(path)),
+                    |(line, column)| format!("{}:{line}:{column}", self.path),
+                );
+            self.list.push(Diagnostic {
+                severity,
+                location,
+                message: message.to_owned(),
+            });
+            return;
+        }
+
+        // Falls back to the closest parent when the exact value is not in the file
+        let offset = (0..=path.len())
+            .rev()
+            .find_map(|len| locate(&self.source, &path[..len]))
+            .map_or(0, |span| span.start);
+
+        self.list.push(Diagnostic {
+            severity,
+            location: self.location(offset),
+            message: message.to_owned(),
+        });
+    }
+
+    fn error_at_file(&mut self, location: &str, message: &str) {
+        self.list.push(Diagnostic {
+            severity: Severity::Error,
+            location: location.to_owned(),
+            message: message.to_owned(),
+        });
+    }
+
+    fn location(&self, offset: usize) -> String {
+        if self.document.is_some() {
+            return self.path.to_owned();
+        }
+
+        let (line, column) = line_and_column(&self.source, offset);
+        format!("{}:{line}:{column}", self.path)
+    }
+
+    // serde_json reports positions relative to the text it was given, which for a
+    // single element starts at `start` in the file
+    fn json_error(&mut self, start: usize, error: &serde_json::Error) {
+        let text = &self.source[start..];
+        let line_start: usize = text
+            .split_inclusive('\n')
+            .take(error.line().saturating_sub(1))
+            .map(str::len)
+            .sum();
+        let offset = (start + line_start + error.column().saturating_sub(1)).min(self.source.len());
+
+        self.list.push(Diagnostic {
+            severity: Severity::Error,
+            location: self.location(offset),
+            message: json_message(error),
+        });
+    }
+
+    fn parse<T: DeserializeOwned>(&mut self, path: &[Key<'_>]) -> Option<T> {
+        let span = locate(&self.source, path)?;
+        match serde_json::from_str(&self.source[span.clone()]) {
+            Err(e) => {
+                // The settings of a check type are only checked once the whole element
+                // has been read, so serde points at its end. Point at the field instead
+                let message = json_message(&e);
+                let unknown_field = message
+                    .strip_prefix("unknown field `")
+                    .and_then(|rest| rest.split('`').next());
+
+                if let Some(field) = unknown_field {
+                    self.error(&[path, &[Key::Field(field)]].concat(), &message);
+                } else if message.starts_with("missing field") || self.document.is_some() {
+                    self.error(path, &message);
+                } else {
+                    self.json_error(span.start, &e);
+                }
+                None
+            }
+            Ok(value) => Some(value),
+        }
+    }
+
+    // Parses each element of a top level array, keeping the index of the ones that parse
+    fn parse_list<T: DeserializeOwned>(&mut self, field: &str) -> Vec<(usize, T)> {
+        let mut parsed = vec![];
+        for i in 0.. {
+            let path = [Key::Field(field), Key::Index(i)];
+            if locate(&self.source, &path).is_none() {
+                break;
+            }
+            if let Some(value) = self.parse(&path) {
+                parsed.push((i, value));
+            }
+        }
+        parsed
+    }
+}
+
+// Columns are counted in bytes, like serde_json does
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+// TOML and YAML values don't keep their position once parsed. Instead the file is read
+// again down to the value at `path`, and reading fails there on purpose. Both libraries
+// add the position of the value they were reading to the error
+fn locate_in_document(file_path: &str, document: &str, path: &[Key<'_>]) -> Option<(usize, usize)> {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())?;
+
+    match extension {
+        "toml" => {
+            let error = PathSeed(path)
+                .deserialize(toml::Deserializer::new(document))
+                .err()
+                .filter(|e| e.message() == PathSeed::FOUND)?;
+            Some(line_and_column(document, error.span()?.start))
+        }
+        "yaml" | "yml" => {
+            let error = PathSeed(path)
+                .deserialize(serde_yaml::Deserializer::from_str(document))
+                .err()
+                .filter(|e| e.to_string().contains(PathSeed::FOUND))?;
+            let location = error.location()?;
+            Some((location.line(), location.column()))
+        }
+        _ => None,
+    }
+}
+
+// Reads down to the value at a path, skipping everything else, and fails with `FOUND`
+// once it gets there
+struct PathSeed<'p, 'k>(&'p [Key<'k>]);
+
+impl PathSeed<'_, '_> {
+    const FOUND: &'static str = "the value was found";
+
+    fn found<E: de::Error>(&self) -> std::result::Result<(), E> {
+        if self.0.is_empty() {
+            Err(E::custom(Self::FOUND))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for PathSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PathSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<(), E> {
+        self.found()
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Err(de::Error::custom(Self::FOUND));
+        };
+
+        while let Some(key) = map.next_key::<String>()? {
+            if matches!(first, Key::Field(field) if *field == key) {
+                map.next_value_seed(PathSeed(rest))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Err(de::Error::custom(Self::FOUND));
+        };
+
+        for i in 0.. {
+            let more = if matches!(first, Key::Index(index) if *index == i) {
+                seq.next_element_seed(PathSeed(rest))?.is_some()
+            } else {
+                seq.next_element::<IgnoredAny>()?.is_some()
+            };
+            if !more {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A path like `checks[1].port`, for when the position is not known
+fn describe_path(path: &[Key<'_>]) -> String {
+    let mut description = String::new();
+    for key in path {
+        match key {
+            Key::Field(field) if description.is_empty() => description.push_str(field),
+            Key::Field(field) => {
+                description.push('.');
+                description.push_str(field);
+            }
+            Key::Index(index) => {
+                description.push('[');
+                description.push_str(&index.to_string());
+                description.push(']');
+            }
+        }
+    }
+    description
+}
+
+// serde_json adds the position to the end of its messages, which is reported separately
+fn json_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message)
+        .to_owned()
+}
+
+#[derive(Clone, Copy)]
+enum Key<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+// Finds where the value at `path` is in the JSON source. Parsed values do not keep
+// their position, so problems found after parsing are located with this
+fn locate(source: &str, path: &[Key<'_>]) -> Option<Range<usize>> {
+    let mut scanner = Scanner {
+        source: source.as_bytes(),
+        pos: 0,
+    };
+    scanner.locate(path)
+}
+
+struct Scanner<'a> {
+    source: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn locate(&mut self, path: &[Key<'_>]) -> Option<Range<usize>> {
+        self.skip_whitespace();
+        let Some((key, rest)) = path.split_first() else {
+            let start = self.pos;
+            self.skip_value()?;
+            return Some(start..self.pos);
+        };
+
+        match (key, self.peek()?) {
+            (Key::Field(name), b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? != b'"' {
+                        return None;
+                    }
+                    let field = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    if field == *name {
+                        return self.locate(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    self.expect(b',')?;
+                }
+            }
+            (Key::Index(index), b'[') => {
+                self.pos += 1;
+                for i in 0.. {
+                    self.skip_whitespace();
+                    if self.peek()? == b']' {
+                        return None;
+                    }
+                    if i == *index {
+                        return self.locate(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    self.expect(b',')?;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => self.string().map(|_| ()),
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Some(());
+                    }
+                }
+            }
+            _ => {
+                while self.peek().is_some_and(|byte| {
+                    !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+                }) {
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        serde_json::from_slice(&self.source[start..self.pos]).ok()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.pos).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes the files into a directory of their own and validates the config in it
+    fn validate(test: &str, files: &[(&str, &str)]) -> Vec<String> {
+        let dir = std::env::temp_dir().join(format!("nanowatchrs_validate_test_{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (name, contents) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let config_path = dir.join(files[0].0).display().to_string();
+        let diagnostics = validate_config(&config_path)
+            .iter()
+            .map(|diagnostic| {
+                diagnostic
+                    .to_string()
+                    .replace(&format!("{}/", dir.display()), "")
+            })
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        diagnostics
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let config = r#"{"settings": {"site": {"name": "Test"}}, "checks": [], "incidents": []}"#;
+        assert_eq!(
+            validate("valid", &[("config.json", config)]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn locates_nested_paths_in_json() {
+        let source = r#"{"checks": [{"name": "A"}, {"name": "B", "headers": {"X": "1"}}]}"#;
+        let path = [
+            Key::Field("checks"),
+            Key::Index(1),
+            Key::Field("headers"),
+            Key::Field("X"),
+        ];
+
+        let range = locate(source, &path).unwrap();
+        assert_eq!(&source[range], r#""1""#);
+        assert_eq!(locate(source, &[Key::Field("checks"), Key::Index(2)]), None);
+    }
+
+    #[test]
+    fn reports_every_broken_check() {
+        let config = r#"{
+  "settings": {"site": {"name": "Test"}},
+  "checks": [
+    {"name": "A", "type": "port", "target": "example.com"},
+    {"name": "B", "type": "ping", "target": "example.com", "expected_status": 200}
+  ]
+}"#;
+
+        assert_eq!(
+            validate("checks", &[("config.json", config)]),
+            [
+                "error: config.json:4:5: missing field `port`",
+                "error: config.json:5:79: unknown field `expected_status`, there are no fields",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_names_across_checks_files() {
+        let config = r#"{
+  "settings": {"site": {"name": "Test"}},
+  "checks": [{"name": "API", "type": "ping", "target": "example.com"}],
+  "incidents": []
+}"#;
+        let checks = "checks:\n  - name: API\n    type: ping\n    target: example.org\n";
+
+        assert_eq!(
+            validate(
+                "duplicates",
+                &[("config.json", config), ("checks.d/api.yaml", checks)]
+            ),
+            ["error: checks.d/api.yaml: checks[0].name: duplicate check name 'API', names must be unique"]
+        );
+    }
+
+    #[test]
+    fn reports_a_bad_incident_date() {
+        let config = r#"{
+  "settings": {"site": {"name": "Test"}},
+  "checks": [],
+  "incidents": [{"title": "Outage", "status": "resolved", "started_at": "yesterday"}]
+}"#;
+
+        assert_eq!(
+            validate("incident_date", &[("config.json", config)]),
+            ["error: config.json:4:84: input contains invalid characters"]
+        );
+    }
+
+    #[test]
+    fn reports_a_missing_include() {
+        let config = r#"{
+  "settings": {"site": {"name": "Test"}, "build": {"theme_dir": "THEME"}},
+  "checks": [],
+  "incidents": []
+}"#;
+        let template = "<p>\n{% include \"missing.html\" %}\n</p>\n";
+
+        let dir = std::env::temp_dir().join("nanowatchrs_validate_test_include");
+        let config = config.replace("THEME", &dir.join("theme").display().to_string());
+        assert_eq!(
+            validate(
+                "include",
+                &[
+                    ("config.json", &config),
+                    ("theme/templates/footer.html", template)
+                ]
+            ),
+            ["error: theme/templates/footer.html:2: template 'missing.html' does not exist"]
+        );
+    }
+
+    #[test]
+    fn counts_columns_after_non_ascii_text() {
+        let config = r#"{"settings": {"site": {"name": "Café ☕"}}, "checks": [{"name": "Ü", "type": "port", "target": "example.com"}], "incidents": []}"#;
+
+        assert_eq!(
+            validate("non_ascii", &[("config.json", config)]),
+            ["error: config.json:1:58: missing field `port`"]
+        );
+    }
+
+    const CHECKS: [Key<'_>; 3] = [Key::Field("checks"), Key::Index(1), Key::Field("port")];
+
+    #[test]
+    fn locates_values_in_toml() {
+        let document = "[settings.site]\nname = \"Test\"\n\n[[checks]]\nname = \"A\"\n\n[[checks]]\nname = \"B\"\nport = 80\n";
+
+        assert_eq!(
+            locate_in_document("config.toml", document, &CHECKS),
+            Some((9, 8))
+        );
+        assert_eq!(
+            locate_in_document("config.toml", document, &CHECKS[..2]),
+            Some((7, 1))
+        );
+        assert_eq!(
+            locate_in_document("config.toml", document, &[Key::Field("missing")]),
+            None
+        );
+    }
+
+    #[test]
+    fn locates_values_in_yaml() {
+        let document =
+            "settings:\n  site:\n    name: Test\nchecks:\n  - name: A\n  - name: B\n    port: 80\n";
+
+        assert_eq!(
+            locate_in_document("config.yaml", document, &CHECKS),
+            Some((7, 11))
+        );
+        assert_eq!(
+            locate_in_document("config.yml", document, &CHECKS[..2]),
+            Some((6, 5))
+        );
+        assert_eq!(
+            locate_in_document(
+                "config.yaml",
+                document,
+                &[Key::Field("checks"), Key::Index(5)]
+            ),
+            None
+        );
+    }
+}